@@ -0,0 +1,518 @@
+use std::sync::{Arc, Mutex};
+
+use rustpython_vm as vm;
+use rustpython::vm::{pyclass, pymodule, PyObjectRef, PyPayload, PyResult, VirtualMachine};
+
+use bevy::math::Vec2;
+
+use crate::{components::{Allegiance, WeaponType}, CommandState, BASE_SPEED, PLAYER_LASER_SPEED};
+
+use super::{draw::{named_color, DebugShape, MAX_SHAPES_PER_TICK}, targeting::{solve_intercept, wrap_angle}, watchdog::budget_exceeded_type};
+
+/// State shared between the runtime and the `codepilot` python module.
+/// The runtime refreshes the status before each tick and reads the commands back after it.
+#[derive(Debug)]
+pub struct ScriptIo {
+	pub commands: CommandState,
+	pub status: codepilot::ShipStatus,
+	pub weapons: Vec<codepilot::WeaponStatus>, // the weapons equipped on the player ship
+	pub enemies: Vec<codepilot::Contact>,
+	pub console: Vec<(bool, String)>, // text printed by the script, and whether it went to stderr
+	pub drawings: Vec<DebugShape>, // drawn this tick
+}
+
+impl Default for ScriptIo {
+	fn default() -> Self {
+		Self {
+			commands: CommandState::default(),
+			status: codepilot::ShipStatus::default(),
+			weapons: Vec::new(),
+			enemies: Vec::new(),
+			console: Vec::new(),
+			drawings: Vec::new(),
+		}
+	}
+}
+
+pub type ScriptIoRef = Arc<Mutex<ScriptIo>>;
+
+/// The `codepilot` module scripts use to control the ship, e.g.
+/// `from codepilot import ship; ship.thrust(0.5); ship.turn(-1.0); ship.fire("laser")`
+/// or `from codepilot import intercept; aim = intercept(enemies[0].id)`
+/// or `from codepilot import draw_arrow; draw_arrow(status.position, aim.position, "red")`
+#[pymodule]
+pub(crate) mod codepilot {
+	use super::*;
+	use bevy::render::color::Color;
+	use vm::{builtins::{PyStr, PyStrRef, PyTypeRef}, function::OptionalArg};
+
+	/// Raised when the script runs past its tick budget, it isn't an `Exception`
+	#[pyattr(name = "BudgetExceeded")]
+	fn budget_exceeded(vm: &VirtualMachine) -> PyTypeRef {
+		budget_exceeded_type(vm).clone()
+	}
+
+	#[pyattr]
+	#[pyclass(module = "codepilot", name = "ShipController")]
+	#[derive(Debug, PyPayload)]
+	pub struct ShipController {
+		pub io: ScriptIoRef,
+	}
+
+	#[pyclass]
+	impl ShipController {
+		/// thrust(throttle=1.0): from -1.0 (full reverse) to 1.0 (full forward)
+		#[pymethod]
+		fn thrust(&self, throttle: OptionalArg<f32>, vm: &VirtualMachine) -> PyResult<()> {
+			let throttle = unit_axis("throttle", throttle.unwrap_or(1.), vm)?;
+			self.io.lock().unwrap().commands.throttle = throttle;
+
+			Ok(())
+		}
+
+		/// turn(rate): from -1.0 (full clockwise) to 1.0 (full counter clockwise)
+		#[pymethod]
+		fn turn(&self, rate: f32, vm: &VirtualMachine) -> PyResult<()> {
+			let rate = unit_axis("turn rate", rate, vm)?;
+			self.io.lock().unwrap().commands.turn = rate;
+
+			Ok(())
+		}
+
+		/// fire(weapon="laser"): the laser is always available, anything else has to be equipped
+		#[pymethod]
+		fn fire(&self, weapon: OptionalArg<PyStrRef>, vm: &VirtualMachine) -> PyResult<()> {
+			let name = weapon.as_ref().map_or("laser", |w| w.as_str());
+			let weapon_type = WeaponType::from_name(name)
+				.ok_or_else(|| vm.new_value_error(format!("unknown weapon '{name}'")))?;
+
+			let mut io = self.io.lock().unwrap();
+
+			match weapon_type {
+				WeaponType::Laser => io.commands.fire = true,
+				other => {
+					if !io.weapons.iter().any(|weapon| weapon.weapon_type == other) {
+						return Err(vm.new_value_error(format!("no {name} equipped")));
+					}
+
+					if !io.commands.fire_weapons.contains(&other) {
+						io.commands.fire_weapons.push(other);
+					}
+				}
+			}
+
+			Ok(())
+		}
+
+		/// weapons(): the equipped weapons besides the laser
+		#[pymethod]
+		fn weapons(&self, vm: &VirtualMachine) -> PyObjectRef {
+			let weapons = self.io.lock().unwrap().weapons.clone();
+			let weapons = weapons.into_iter().map(|weapon| weapon.into_pyobject(vm)).collect();
+
+			vm.ctx.new_list(weapons).into()
+		}
+
+		/// enemy(id): the enemy with that id, or None once it is gone
+		#[pymethod]
+		fn enemy(&self, id: u64) -> Option<Contact> {
+			self.io.lock().unwrap().enemies.iter().find(|contact| contact.id == id).cloned()
+		}
+
+		#[pymethod]
+		fn status(&self) -> ShipStatus {
+			self.io.lock().unwrap().status.clone()
+		}
+
+		/// intercept(id): how to aim the laser to hit that enemy where it is going to be,
+		/// None if it's gone or the laser can't catch it. Also available as `codepilot.intercept`
+		#[pymethod]
+		fn intercept(&self, id: u64) -> Option<Intercept> {
+			let io = self.io.lock().unwrap();
+			let target = io.enemies.iter().find(|contact| contact.id == id)?;
+
+			let ship_position = Vec2::from(io.status.position);
+			let (point, time) = solve_intercept(
+				ship_position,
+				Vec2::from(target.position),
+				Vec2::from(target.velocity),
+				PLAYER_LASER_SPEED * BASE_SPEED,
+			)?;
+
+			let aim = point - ship_position;
+			let heading = aim.y.atan2(aim.x);
+
+			Some(Intercept {
+				position: (point.x, point.y),
+				time,
+				heading,
+				bearing: wrap_angle(heading - io.status.heading),
+			})
+		}
+	}
+
+	/// Debug drawing in world space, shown over the game until the next tick.
+	/// The methods are also available as `codepilot.draw_line` and so on.
+	/// Points are (x, y) pairs or anything with x and y, colors are names or (r, g, b) from 0 to 1
+	#[pyattr]
+	#[pyclass(module = "codepilot", name = "DebugDraw")]
+	#[derive(Debug, PyPayload)]
+	pub struct DebugDraw {
+		pub io: ScriptIoRef,
+	}
+
+	#[pyclass]
+	impl DebugDraw {
+		#[pymethod]
+		fn draw_line(&self, start: PyObjectRef, end: PyObjectRef, color: OptionalArg<PyObjectRef>, vm: &VirtualMachine) -> PyResult<()> {
+			let shape = DebugShape::Line { start: point(start, vm)?, end: point(end, vm)?, color: color_arg(color, vm)? };
+			self.push(shape);
+			Ok(())
+		}
+
+		#[pymethod]
+		fn draw_circle(&self, center: PyObjectRef, radius: f32, color: OptionalArg<PyObjectRef>, vm: &VirtualMachine) -> PyResult<()> {
+			let shape = DebugShape::Circle { center: point(center, vm)?, radius, color: color_arg(color, vm)? };
+			self.push(shape);
+			Ok(())
+		}
+
+		#[pymethod]
+		fn draw_arrow(&self, start: PyObjectRef, end: PyObjectRef, color: OptionalArg<PyObjectRef>, vm: &VirtualMachine) -> PyResult<()> {
+			let shape = DebugShape::Arrow { start: point(start, vm)?, end: point(end, vm)?, color: color_arg(color, vm)? };
+			self.push(shape);
+			Ok(())
+		}
+
+		#[pymethod]
+		fn draw_text(&self, position: PyObjectRef, text: PyObjectRef, color: OptionalArg<PyObjectRef>, vm: &VirtualMachine) -> PyResult<()> {
+			let text = text.str(vm)?.as_str().to_owned();
+			let shape = DebugShape::Text { position: point(position, vm)?, text, color: color_arg(color, vm)? };
+			self.push(shape);
+			Ok(())
+		}
+	}
+
+	impl DebugDraw {
+		fn push(&self, shape: DebugShape) {
+			let mut io = self.io.lock().unwrap();
+			if io.drawings.len() < MAX_SHAPES_PER_TICK {
+				io.drawings.push(shape);
+			}
+		}
+	}
+
+	// a tuple, or an object with x and y like the prelude's Vec2
+	fn point(value: PyObjectRef, vm: &VirtualMachine) -> PyResult<Vec2> {
+		if let Ok((x, y)) = value.clone().try_into_value::<(f32, f32)>(vm) {
+			return Ok(Vec2::new(x, y));
+		}
+
+		let x = value.get_attr("x", vm).and_then(|x| x.try_into_value::<f32>(vm));
+		let y = value.get_attr("y", vm).and_then(|y| y.try_into_value::<f32>(vm));
+
+		match (x, y) {
+			(Ok(x), Ok(y)) => Ok(Vec2::new(x, y)),
+			_ => Err(vm.new_type_error(format!("expected an (x, y) point, not {}", value.class().name()))),
+		}
+	}
+
+	fn color_arg(color: OptionalArg<PyObjectRef>, vm: &VirtualMachine) -> PyResult<Color> {
+		let OptionalArg::Present(color) = color else {
+			return Ok(Color::GREEN);
+		};
+
+		if let Some(name) = color.payload::<PyStr>() {
+			return named_color(name.as_str())
+				.ok_or_else(|| vm.new_value_error(format!("unknown color '{}'", name.as_str())));
+		}
+
+		let (r, g, b) = color.try_into_value::<(f32, f32, f32)>(vm)
+			.map_err(|_| vm.new_type_error("color must be a name or an (r, g, b) tuple".to_owned()))?;
+		Ok(Color::rgb(r, g, b))
+	}
+
+	fn unit_axis(name: &str, value: f32, vm: &VirtualMachine) -> PyResult<f32> {
+		if !(-1. ..=1.).contains(&value) {
+			return Err(vm.new_value_error(format!("{name} must be between -1.0 and 1.0, not {value}")));
+		}
+
+		Ok(value)
+	}
+
+	/// Snapshot of the player ship, taken at the start of the tick.
+	/// Also published as the `status` global
+	#[pyattr]
+	#[pyclass(module = "codepilot", name = "ShipStatus")]
+	#[derive(Debug, Clone, Default, PyPayload)]
+	pub struct ShipStatus {
+		pub position: (f32, f32),
+		pub velocity: (f32, f32), // world units per second
+		pub angular_velocity: f32, // radians per second, counter-clockwise
+		pub heading: f32, // radians
+		pub shields: f32,
+		pub max_shields: f32,
+		pub weapon_cooldown: f32, // seconds until the laser can fire again
+		pub weapon_cooldown_max: f32, // seconds between laser shots
+		pub emp_charge: Option<f32>, // None until the EMP is equipped
+		pub score: u32,
+		pub time: f64, // seconds since the game started
+	}
+
+	#[pyclass]
+	impl ShipStatus {
+		#[pygetset]
+		fn position(&self) -> (f32, f32) {
+			self.position
+		}
+
+		#[pygetset]
+		fn velocity(&self) -> (f32, f32) {
+			self.velocity
+		}
+
+		#[pygetset]
+		fn angular_velocity(&self) -> f32 {
+			self.angular_velocity
+		}
+
+		#[pygetset]
+		fn heading(&self) -> f32 {
+			self.heading
+		}
+
+		#[pygetset]
+		fn shields(&self) -> f32 {
+			self.shields
+		}
+
+		#[pygetset]
+		fn max_shields(&self) -> f32 {
+			self.max_shields
+		}
+
+		// shields as a fraction of the maximum, from 0 to 1
+		#[pygetset]
+		fn shield_fraction(&self) -> f32 {
+			if self.max_shields > 0. { self.shields / self.max_shields } else { 0. }
+		}
+
+		#[pygetset]
+		fn weapon_cooldown(&self) -> f32 {
+			self.weapon_cooldown
+		}
+
+		#[pygetset]
+		fn weapon_cooldown_max(&self) -> f32 {
+			self.weapon_cooldown_max
+		}
+
+		#[pygetset]
+		fn emp_charge(&self) -> Option<f32> {
+			self.emp_charge
+		}
+
+		#[pygetset]
+		fn score(&self) -> u32 {
+			self.score
+		}
+
+		#[pygetset]
+		fn time(&self) -> f64 {
+			self.time
+		}
+	}
+
+	/// Replaces `sys.stdout` and `sys.stderr` so `print` output ends up in the console panel
+	#[pyattr]
+	#[pyclass(module = "codepilot", name = "ConsoleWriter")]
+	#[derive(Debug, PyPayload)]
+	pub struct ConsoleWriter {
+		pub io: ScriptIoRef,
+		pub is_error: bool,
+	}
+
+	#[pyclass]
+	impl ConsoleWriter {
+		#[pymethod]
+		fn write(&self, text: PyStrRef) -> usize {
+			let text = text.as_str();
+			self.io.lock().unwrap().console.push((self.is_error, text.to_owned()));
+			text.chars().count()
+		}
+
+		#[pymethod]
+		fn flush(&self) {}
+	}
+
+	/// An enemy ship, taken at the start of the tick.
+	/// The id stays the same for as long as the ship is alive
+	#[pyattr]
+	#[pyclass(module = "codepilot", name = "Contact")]
+	#[derive(Debug, Clone, PyPayload)]
+	pub struct Contact {
+		pub id: u64,
+		pub position: (f32, f32),
+		pub velocity: (f32, f32), // world units per second
+		pub heading: f32, // radians
+		pub shields: f32,
+		pub max_shields: f32,
+		pub allegiance: Allegiance,
+	}
+
+	#[pyclass]
+	impl Contact {
+		#[pygetset]
+		fn id(&self) -> u64 {
+			self.id
+		}
+
+		#[pygetset]
+		fn position(&self) -> (f32, f32) {
+			self.position
+		}
+
+		#[pygetset]
+		fn velocity(&self) -> (f32, f32) {
+			self.velocity
+		}
+
+		#[pygetset]
+		fn heading(&self) -> f32 {
+			self.heading
+		}
+
+		#[pygetset]
+		fn shields(&self) -> f32 {
+			self.shields
+		}
+
+		#[pygetset]
+		fn max_shields(&self) -> f32 {
+			self.max_shields
+		}
+
+		#[pygetset]
+		fn allegiance(&self) -> &'static str {
+			self.allegiance.name()
+		}
+
+		#[pymethod(magic)]
+		fn repr(&self) -> String {
+			format!("<Contact {} at ({:.0}, {:.0})>", self.id, self.position.0, self.position.1)
+		}
+	}
+
+	/// A laser within sensor range, taken at the start of the tick
+	#[pyattr]
+	#[pyclass(module = "codepilot", name = "Projectile")]
+	#[derive(Debug, Clone, PyPayload)]
+	pub struct Projectile {
+		pub position: (f32, f32),
+		pub velocity: (f32, f32), // world units per second
+		pub allegiance: Allegiance,
+		pub time_to_closest_approach: f32, // seconds, 0 once it is moving away from the ship
+		pub closest_approach: f32, // distance from the ship at that time
+	}
+
+	#[pyclass]
+	impl Projectile {
+		#[pygetset]
+		fn position(&self) -> (f32, f32) {
+			self.position
+		}
+
+		#[pygetset]
+		fn velocity(&self) -> (f32, f32) {
+			self.velocity
+		}
+
+		#[pygetset]
+		fn allegiance(&self) -> &'static str {
+			self.allegiance.name()
+		}
+
+		#[pygetset]
+		fn time_to_closest_approach(&self) -> f32 {
+			self.time_to_closest_approach
+		}
+
+		#[pygetset]
+		fn closest_approach(&self) -> f32 {
+			self.closest_approach
+		}
+	}
+
+	/// A firing solution from `ship.intercept(id)`
+	#[pyattr]
+	#[pyclass(module = "codepilot", name = "Intercept")]
+	#[derive(Debug, Clone, PyPayload)]
+	pub struct Intercept {
+		pub position: (f32, f32), // where the laser meets the enemy
+		pub time: f32, // seconds until it does, if fired now
+		pub heading: f32, // radians, the heading to fire at
+		pub bearing: f32, // radians to turn from the current heading, positive is counter clockwise
+	}
+
+	#[pyclass]
+	impl Intercept {
+		#[pygetset]
+		fn position(&self) -> (f32, f32) {
+			self.position
+		}
+
+		#[pygetset]
+		fn time(&self) -> f32 {
+			self.time
+		}
+
+		#[pygetset]
+		fn heading(&self) -> f32 {
+			self.heading
+		}
+
+		#[pygetset]
+		fn bearing(&self) -> f32 {
+			self.bearing
+		}
+
+		#[pymethod(magic)]
+		fn repr(&self) -> String {
+			format!("<Intercept at ({:.0}, {:.0}) in {:.2}s, bearing {:.2}>", self.position.0, self.position.1, self.time, self.bearing)
+		}
+	}
+
+	/// A `Weapon` on the player ship, taken at the start of the tick
+	#[pyattr]
+	#[pyclass(module = "codepilot", name = "WeaponStatus")]
+	#[derive(Debug, Clone, PyPayload)]
+	pub struct WeaponStatus {
+		pub weapon_type: WeaponType,
+		pub current_charge: f32,
+		pub charge_rate: f32, // per second
+	}
+
+	#[pyclass]
+	impl WeaponStatus {
+		#[pygetset(name = "type")]
+		fn weapon_type(&self) -> &'static str {
+			self.weapon_type.name()
+		}
+
+		#[pygetset]
+		fn current_charge(&self) -> f32 {
+			self.current_charge
+		}
+
+		#[pygetset]
+		fn charge_rate(&self) -> f32 {
+			self.charge_rate
+		}
+
+		// weapons fire once fully charged
+		#[pygetset]
+		fn ready(&self) -> bool {
+			self.current_charge >= 1.
+		}
+	}
+}
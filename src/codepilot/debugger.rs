@@ -0,0 +1,239 @@
+use std::{collections::BTreeSet, sync::{Arc, Mutex}};
+
+use bevy::prelude::*;
+use rustpython_vm as vm;
+use vm::{builtins::{PyDict, PyModule, PyStr}, function::FuncArgs, PyObjectRef, PyResult, VirtualMachine};
+
+use crate::CODEPILOT_MAIN_FILE;
+
+use super::{runtime::PROVIDED_GLOBALS, sandbox::SCRIPT_MODULE_NAME, watchdog::WatchdogPauser};
+
+// Stops recorded after a breakpoint is hit, so a long loop can't record forever
+const MAX_DEBUG_STOPS: usize = 500;
+// Longer variable reprs are cut off
+const MAX_VALUE_LEN: usize = 120;
+// Variables recorded per scope at each stop, in name order, so 500 stops stay a bounded amount of reprs
+const MAX_STOP_VARIABLES: usize = 50;
+
+/// Resource - the breakpoints set in the editor and, while the game is paused on one, the
+/// stops recorded during that tick.
+///
+/// A script can't be suspended halfway through a tick, so the tick runs to the end with the
+/// tracer recording a stop for every line it passes after the breakpoint. Stepping then walks
+/// through those stops, and the tick's commands are only acted on once the game resumes.
+#[derive(Resource, Default)]
+pub struct ScriptDebugger {
+	pub breakpoints: BTreeSet<(String, usize)>, // file, 1 based line
+	pub stops: Vec<DebugStop>,
+	pub current: Option<usize>, // the stop being shown, the game is paused while set
+}
+
+impl ScriptDebugger {
+	pub fn is_paused(&self) -> bool {
+		self.current.is_some()
+	}
+
+	pub fn current_stop(&self) -> Option<&DebugStop> {
+		self.stops.get(self.current?)
+	}
+
+	pub fn toggle_breakpoint(&mut self, file: &str, line: usize) {
+		let breakpoint = (file.to_owned(), line);
+		if !self.breakpoints.remove(&breakpoint) {
+			self.breakpoints.insert(breakpoint);
+		}
+	}
+
+	pub fn has_breakpoint(&self, file: &str, line: usize) -> bool {
+		self.breakpoints.contains(&(file.to_owned(), line))
+	}
+
+	// returns false once there's nothing left to show
+	pub fn step(&mut self) -> bool {
+		self.advance(|_| true)
+	}
+
+	pub fn continue_to_breakpoint(&mut self) -> bool {
+		self.advance(|stop| stop.is_breakpoint)
+	}
+
+	fn advance(&mut self, is_target: impl Fn(&DebugStop) -> bool) -> bool {
+		let Some(current) = self.current else {
+			return false;
+		};
+
+		self.current = self.stops.iter().enumerate()
+			.skip(current + 1)
+			.find(|(_, stop)| is_target(stop))
+			.map(|(index, _)| index);
+
+		self.is_paused()
+	}
+
+	pub fn clear_stops(&mut self) {
+		self.stops.clear();
+		self.current = None;
+	}
+}
+
+/// Where the script was and what its variables held, as `(name, repr(value))`
+#[derive(Clone, Debug)]
+pub struct DebugStop {
+	pub file: String,
+	pub line: usize,
+	pub is_breakpoint: bool,
+	pub locals: Vec<(String, String)>,
+	pub globals: Vec<(String, String)>,
+}
+
+#[derive(Default)]
+struct TraceState {
+	breakpoints: BTreeSet<(String, usize)>,
+	project_modules: Vec<String>,
+	stops: Vec<DebugStop>,
+}
+
+/// Records stops through `sys.settrace`. RustPython reports calls and returns rather than
+/// every line, so breakpoints are hit on lines that call a function, see `line_has_call`.
+pub struct Tracer {
+	state: Arc<Mutex<TraceState>>,
+	pauser: WatchdogPauser, // recording a stop doesn't count toward the tick budget
+}
+
+impl Tracer {
+	pub fn new(project_modules: &[String], pauser: WatchdogPauser) -> Self {
+		let state = TraceState {
+			project_modules: project_modules.to_vec(),
+			..default()
+		};
+
+		Self { state: Arc::new(Mutex::new(state)), pauser }
+	}
+
+	/// Starts tracing with the given breakpoints, tracing is left off when there are none
+	pub fn start(&self, vm: &VirtualMachine, breakpoints: &BTreeSet<(String, usize)>) -> PyResult<()> {
+		{
+			let mut state = self.state.lock().unwrap();
+			state.breakpoints = breakpoints.clone();
+			state.stops.clear();
+		}
+
+		if breakpoints.is_empty() {
+			return Ok(());
+		}
+
+		let state = self.state.clone();
+		let pauser = self.pauser.clone();
+		let trace = vm.new_function("codepilot_trace", move |args: FuncArgs, vm: &VirtualMachine| -> PyResult<()> {
+			// hooks called from rust have no calling frame
+			let Some(frame) = args.args.first().filter(|frame| !vm.is_none(frame)) else {
+				return Ok(());
+			};
+
+			// a failed recording is dropped rather than raised into the script
+			let _ = pauser.paused(|| record_stop(&mut state.lock().unwrap(), frame, vm));
+			Ok(())
+		});
+
+		vm.sys_module.get_attr("settrace", vm)?.call((trace,), vm)?;
+
+		Ok(())
+	}
+
+	/// Stops tracing and returns what was recorded
+	pub fn finish(&self, vm: &VirtualMachine) -> Vec<DebugStop> {
+		if let Ok(settrace) = vm.sys_module.get_attr("settrace", vm) {
+			let _ = settrace.call((vm.ctx.none(),), vm);
+		}
+
+		std::mem::take(&mut self.state.lock().unwrap().stops)
+	}
+}
+
+fn record_stop(state: &mut TraceState, frame: &PyObjectRef, vm: &VirtualMachine) -> PyResult<()> {
+	let globals = frame.get_attr("f_globals", vm)?;
+
+	// only frames of the project files, not the stdlib or the prelude's imports
+	let module = globals.get_item("__name__", vm)?;
+	let Some(module) = module.payload::<PyStr>() else {
+		return Ok(());
+	};
+	let file = match module.as_str() {
+		SCRIPT_MODULE_NAME => CODEPILOT_MAIN_FILE,
+		name if state.project_modules.iter().any(|module| module == name) => name,
+		_ => return Ok(()),
+	};
+
+	let line: usize = frame.get_attr("f_lineno", vm)?.try_to_value(vm)?;
+	let is_breakpoint = state.breakpoints.contains(&(file.to_owned(), line));
+
+	// nothing is recorded until a breakpoint is hit, after that every new line is a step
+	let recording = !state.stops.is_empty();
+	let same_line = state.stops.last().is_some_and(|stop| stop.file == file && stop.line == line);
+
+	if (!recording && !is_breakpoint) || same_line || state.stops.len() >= MAX_DEBUG_STOPS {
+		return Ok(());
+	}
+
+	let locals = frame.get_attr("f_locals", vm)?;
+	let is_module_level = locals.is(&globals);
+
+	state.stops.push(DebugStop {
+		file: file.to_owned(),
+		line,
+		is_breakpoint,
+		locals: if is_module_level { Vec::new() } else { variables(&locals, &[], vm) },
+		globals: variables(&globals, &PROVIDED_GLOBALS, vm),
+	});
+
+	Ok(())
+}
+
+// the value's repr, cut off if it's long
+pub fn short_repr(value: &PyObjectRef, vm: &VirtualMachine) -> Option<String> {
+	let mut repr = value.repr(vm).ok()?.as_str().to_owned();
+	if let Some((cut, _)) = repr.char_indices().nth(MAX_VALUE_LEN) {
+		repr.truncate(cut);
+		repr.push_str("...");
+	}
+
+	Some(repr)
+}
+
+// whether a global is the script's own state rather than a definition or an import
+pub fn is_variable(name: &str, value: &PyObjectRef) -> bool {
+	!name.starts_with("__") && !value.payload_is::<PyModule>() && value.to_callable().is_none()
+}
+
+// the script's own variables, leaving out dunders, modules, anything callable and the names in `skip`
+fn variables(namespace: &PyObjectRef, skip: &[&str], vm: &VirtualMachine) -> Vec<(String, String)> {
+	let Some(namespace) = namespace.downcast_ref::<PyDict>() else {
+		return Vec::new();
+	};
+
+	let mut variables = namespace.to_owned().into_iter()
+		.filter_map(|(name, value)| {
+			let name = name.payload::<PyStr>()?.as_str().to_owned();
+			(!skip.contains(&name.as_str()) && is_variable(&name, &value)).then_some((name, value))
+		})
+		.collect::<Vec<_>>();
+
+	variables.sort_by(|(a, _), (b, _)| a.cmp(b));
+	variables.truncate(MAX_STOP_VARIABLES);
+
+	variables.into_iter()
+		.filter_map(|(name, value)| Some((name, short_repr(&value, vm)?)))
+		.collect()
+}
+
+// whether the tracer can stop on the line, it's only told about calls. Lexical, so a `(` in a
+// string or comment counts too
+pub fn line_has_call(line: &str) -> bool {
+	let code = line.split('#').next().unwrap_or_default();
+
+	code.char_indices().any(|(index, c)| {
+		c == '(' && code[..index].trim_end().chars().next_back().is_some_and(|before| {
+			before.is_alphanumeric() || before == '_' || before == ')' || before == ']'
+		})
+	})
+}
@@ -0,0 +1,123 @@
+use bevy::{prelude::*, text::BreakLineOn};
+
+use crate::components::ScriptDrawingText;
+
+// More shapes than this in one tick are ignored
+pub const MAX_SHAPES_PER_TICK: usize = 500;
+
+const ARROW_HEAD_LENGTH: f32 = 12.;
+const ARROW_HEAD_ANGLE: f32 = 0.5; // radians either side of the shaft
+const TEXT_SIZE: f32 = 18.;
+const TEXT_Z: f32 = 50.; // above the ships
+
+/// Something the script drew this tick, in world space
+#[derive(Clone, Debug)]
+pub enum DebugShape {
+	Line { start: Vec2, end: Vec2, color: Color },
+	Circle { center: Vec2, radius: f32, color: Color },
+	Arrow { start: Vec2, end: Vec2, color: Color },
+	Text { position: Vec2, text: String, color: Color },
+}
+
+/// Resource - the shapes drawn by the script on its last tick
+#[derive(Resource)]
+pub struct ScriptDrawings {
+	pub shapes: Vec<DebugShape>,
+	pub visible: bool,
+}
+
+impl Default for ScriptDrawings {
+	fn default() -> Self {
+		Self {
+			shapes: Vec::new(),
+			visible: true,
+		}
+	}
+}
+
+// color names scripts can use, anything else has to be an (r, g, b) tuple
+pub fn named_color(name: &str) -> Option<Color> {
+	let color = match name {
+		"red" => Color::RED,
+		"green" => Color::GREEN,
+		"blue" => Color::BLUE,
+		"yellow" => Color::YELLOW,
+		"orange" => Color::ORANGE,
+		"cyan" => Color::CYAN,
+		"magenta" => Color::FUCHSIA,
+		"white" => Color::WHITE,
+		"gray" | "grey" => Color::GRAY,
+		_ => return None,
+	};
+
+	Some(color)
+}
+
+// gizmos are immediate mode, so the shapes are drawn again every frame
+pub fn script_drawing_system(drawings: Res<ScriptDrawings>, mut gizmos: Gizmos) {
+	if !drawings.visible {
+		return;
+	}
+
+	for shape in drawings.shapes.iter() {
+		match *shape {
+			DebugShape::Line { start, end, color } => gizmos.line_2d(start, end, color),
+			DebugShape::Circle { center, radius, color } => {
+				gizmos.circle_2d(center, radius, color);
+			}
+			DebugShape::Arrow { start, end, color } => {
+				gizmos.line_2d(start, end, color);
+
+				let back = (start - end).normalize_or_zero() * ARROW_HEAD_LENGTH;
+				for side in [ARROW_HEAD_ANGLE, -ARROW_HEAD_ANGLE] {
+					gizmos.line_2d(end, end + Vec2::from_angle(side).rotate(back), color);
+				}
+			}
+			DebugShape::Text { .. } => {}
+		}
+	}
+}
+
+// text needs entities, they're respawned whenever the script draws a new tick
+pub fn script_drawing_text_system(
+	mut commands: Commands,
+	drawings: Res<ScriptDrawings>,
+	asset_server: Res<AssetServer>,
+	text_query: Query<Entity, With<ScriptDrawingText>>,
+) {
+	if !drawings.is_changed() {
+		return;
+	}
+
+	for entity in text_query.iter() {
+		commands.entity(entity).despawn();
+	}
+
+	if !drawings.visible {
+		return;
+	}
+
+	for shape in drawings.shapes.iter() {
+		let DebugShape::Text { position, ref text, color } = *shape else {
+			continue;
+		};
+
+		commands.spawn(Text2dBundle {
+			text: Text {
+				sections: vec![TextSection::new(
+					text.clone(),
+					TextStyle {
+						font: asset_server.load("fonts/ShareTechMono-Regular.ttf"),
+						font_size: TEXT_SIZE,
+						color,
+					},
+				)],
+				alignment: TextAlignment::Left,
+				linebreak_behavior: BreakLineOn::NoWrap,
+			},
+			transform: Transform::from_translation(position.extend(TEXT_Z)),
+			..default()
+		})
+		.insert(ScriptDrawingText);
+	}
+}
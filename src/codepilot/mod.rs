@@ -0,0 +1,775 @@
+use std::collections::HashMap;
+
+use bevy::{prelude::*, utils::HashSet, sprite::{collide_aabb::collide, MaterialMesh2dBundle, Mesh2dHandle}, render::mesh};
+
+use rustpython_vm as vm;
+use vm::{builtins::{PyFloat, PyInt, PyStr}, PyObjectRef};
+use vm::builtins::PyList;
+use rustpython::vm::{
+    pyclass, pymodule, PyObject, PyPayload, PyResult, TryFromBorrowedObject, VirtualMachine, stdlib
+};
+use vm::convert::ToPyObject;
+
+use crate::{components::{Allegiance, Enemy, Laser, Player, Ship, Velocity, Weapon, WeaponType}, events::{ClearMemoryEvent, CompileCodeEvent, DebuggerActionEvent, FireWeaponEvent, ScriptCompiledEvent, PlayerDestroyedEvent, PlayerSpawnedEvent, ShipHitEvent}, player::{accelerate_backward, accelerate_clockwise, accelerate_counter_clockwise, accelerate_forward, try_fire_weapon}, CodePilotCode, CodePilotHist, CodePilotOutput, CodePilotSettings, CommandState, ConsoleLine, GameTextures, KeyedDebug, PlayerState, PyDebugMessage, WatchValue, BASE_ROT_SPEED, BASE_SPEED, CODEPILOT_CONSOLE_LINES_PER_TICK, CODEPILOT_MAIN_FILE, CODEPILOT_MAX_FAILED_TICKS, CODEPILOT_RESUME_KEY, CODEPILOT_SENSOR_RANGE, REFERENCE_FRAME_RATE};
+
+use self::api::codepilot::{Contact, Projectile, ShipStatus, WeaponStatus};
+use self::runtime::{reset_tick_globals, CodePilotRuntime, LifecycleHook, ScriptSession};
+
+mod api;
+mod debugger;
+mod draw;
+mod plots;
+mod project;
+mod runtime;
+mod sandbox;
+mod targeting;
+mod watchdog;
+
+pub use self::debugger::{line_has_call, ScriptDebugger};
+pub use self::draw::ScriptDrawings;
+pub use self::plots::DebugPlots;
+pub use self::sandbox::SandboxPolicy;
+
+macro_rules! add_python_function {
+    ( $scope:ident, $vm:ident, $src:literal $(,)? ) => {{
+        // compile the code to bytecode
+        let code = vm::py_compile!(source = $src);
+        // convert the rustpython_compiler_core::CodeObject to a PyRef<PyCode>
+        let code = $vm.ctx.new_code(code);
+
+        // run the python code in the scope to store the function
+        $vm.run_code_obj(code, $scope.clone())
+    }};
+}
+
+// removes the line in a string for a given index
+fn remove_line(input_sting: &str, line_index: usize) -> String {
+	let mut lines = input_sting.lines().collect::<Vec<_>>();
+	lines.remove(line_index);
+	lines.join("\n")
+}
+
+
+pub struct CodePilotPlugin;
+
+impl Plugin for CodePilotPlugin {
+	fn build(&self, app: &mut App) {
+		app
+        .insert_non_send_resource(CodePilotRuntime::default())
+        .init_resource::<LatchedCommands>()
+        .init_resource::<ScriptDebugger>()
+        .init_resource::<ScriptDrawings>()
+        .init_resource::<DebugPlots>()
+        .add_event::<ScriptCompiledEvent>()
+        .add_event::<ClearMemoryEvent>()
+        .add_event::<DebuggerActionEvent>()
+        .add_systems(Update, player_codepilot_compile_system)
+        .add_systems(Update, codepilot_tick_rate_system)
+        .add_systems(FixedUpdate, codepilot_tick_system)
+        .add_systems(Update, codepilot_actuation_system.run_if(debugger_running))
+        .add_systems(Update, codepilot_debugger_system)
+        .add_systems(Update, draw::script_drawing_system)
+        .add_systems(Update, draw::script_drawing_text_system)
+        .add_systems(Update, codepilot_resume_system)
+        .add_systems(Update, codepilot_clear_memory_system)
+        .add_systems(Update, codepilot_lifecycle_system);
+
+	}
+}
+
+/// Resource - the commands from the last codepilot tick, latched until the next tick
+#[derive(Resource)]
+pub struct LatchedCommands(pub CommandState);
+
+impl Default for LatchedCommands {
+	fn default() -> Self {
+		Self(CommandState::default())
+	}
+}
+
+#[derive(Debug, Clone)]
+struct PyAccessibleV3Vec(Vec<Vec3>);
+impl ToPyObject for PyAccessibleV3Vec {
+	fn to_pyobject(self, vm: &VirtualMachine) -> PyObjectRef {
+		let list: Vec<PyObjectRef>= self.0.into_iter().map(
+			|e| {
+				vm.new_pyobj((e.x, e.y, e.z))
+			} 
+		).collect();
+		PyList::new_ref(list, vm.as_ref()).to_pyobject(vm)
+	}
+}
+fn player_codepilot_compile_system(
+	mut commands: Commands,
+	mut compile_code_event: EventReader<CompileCodeEvent>,
+	mut codepilot_code: ResMut<CodePilotCode>,
+	mut latched_commands: ResMut<LatchedCommands>,
+	mut runtime: NonSendMut<CodePilotRuntime>,
+	settings: Res<CodePilotSettings>,
+	mut script_compiled_event: EventWriter<ScriptCompiledEvent>,
+) {
+	for ev in compile_code_event.read() {
+		// the whole project is compiled, the main file is the script that runs every tick
+		let mut modules = codepilot_code.project_sources();
+		let source = modules.remove(CODEPILOT_MAIN_FILE).unwrap_or_default();
+
+		// a failed compile leaves the previous session running
+		codepilot_code.compile_result = None;
+		match ScriptSession::compile(&source, &modules, &settings.sandbox) {
+			Ok(session) => {
+				if settings.keep_memory_on_recompile {
+					let memory = codepilot_code.memory.clone();
+					let dropped = session.enter(|vm, _| session.restore_memory(vm, &memory));
+
+					if !dropped.is_empty() {
+						codepilot_code.compile_result = Some(format!("memory: could not carry over {}, only python literals are kept", dropped.join(", ")));
+					}
+				} else {
+					codepilot_code.memory = Vec::new();
+				}
+
+				codepilot_code.compiled = Some(session.code());
+				codepilot_code.failed_ticks = 0;
+				codepilot_code.faulted = false;
+				latched_commands.0 = CommandState::default();
+				runtime.session = Some(session);
+				script_compiled_event.send(ScriptCompiledEvent);
+			}
+			Err(err) => {
+				codepilot_code.compile_result = Some(err);
+			}
+		}
+    }
+}
+
+fn try_boolean_python_action (key: &str, scope: &vm::scope::Scope, vm: &VirtualMachine) -> bool {
+	let fire = scope.globals.get_item(key, vm);
+				
+	if let Ok(fire_ref) = fire {
+		let fire_bool_res = fire_ref.is_true(vm);
+		if let Ok(fire_bool) = fire_bool_res {
+			info!("fire: {}", fire_bool);
+
+			if fire_bool {
+				return true;
+			}
+		}
+	}
+
+		return false;
+}
+
+fn get_last_debug_message_list(hist: &CodePilotHist) -> Option<Vec<PyDebugMessage>> {
+	for (_, hist_item) in hist.iter().rev() {
+		match hist_item {
+			CodePilotOutput::DebugMessages(debug_messages) => {
+				return Some(debug_messages.clone());
+			},
+			_ => {}
+		}
+	}
+	None
+}
+
+fn get_last_command_state(hist: &CodePilotHist) -> Option<CommandState> {
+	for (_, hist_item) in hist.iter().rev() {
+		match hist_item {
+			CodePilotOutput::CommandState(command_state) => {
+				return Some(command_state.clone());
+			},
+			_ => {}
+		}
+	}
+	None
+}
+
+// the lasers within sensor range of the ship, with where and when they pass closest to it
+fn scan_projectiles<'a>(
+	ship_tf: &Transform,
+	ship_velocity: &Velocity,
+	lasers: impl Iterator<Item = (&'a Velocity, &'a Transform, &'a Allegiance)>,
+) -> Vec<Projectile> {
+	let ship_position = ship_tf.translation.truncate();
+	let ship_velocity = Vec2::new(ship_velocity.x, ship_velocity.y) * BASE_SPEED;
+
+	lasers
+		.filter(|(_, laser_tf, _)| laser_tf.translation.truncate().distance(ship_position) <= CODEPILOT_SENSOR_RANGE)
+		.map(|(velocity, laser_tf, &allegiance)| {
+			let velocity = Vec2::new(velocity.x, velocity.y) * BASE_SPEED;
+
+			// relative motion in world units per second
+			let offset = laser_tf.translation.truncate() - ship_position;
+			let closing_velocity = velocity - ship_velocity;
+
+			let speed_squared = closing_velocity.length_squared();
+			let time_to_closest_approach = if speed_squared > 0. {
+				(-offset.dot(closing_velocity) / speed_squared).max(0.)
+			} else {
+				0.
+			};
+
+			Projectile {
+				position: (laser_tf.translation.x, laser_tf.translation.y),
+				velocity: (velocity.x, velocity.y),
+				allegiance,
+				time_to_closest_approach,
+				closest_approach: (offset + closing_velocity * time_to_closest_approach).length(),
+			}
+		})
+		.collect()
+}
+
+// runs the script once per fixed tick and latches the commands it issues
+fn codepilot_tick_system(
+	mut codepilot_code: ResMut<CodePilotCode>,
+	mut latched_commands: ResMut<LatchedCommands>,
+	mut runtime: NonSendMut<CodePilotRuntime>,
+	player_state: Res<PlayerState>,
+	time: Res<Time>,
+	query: Query<(&Velocity, &Transform, &Ship, Option<&Children>), With<Player>>,
+	weapon_query: Query<(&Weapon, &WeaponType)>,
+	enemy_query: Query<(Entity, &Velocity, &Transform, &Ship, &Allegiance), (Without<Player>, With<Enemy>)>,
+	laser_query: Query<(&Velocity, &Transform, &Allegiance), With<Laser>>,
+	mut debugger: ResMut<ScriptDebugger>,
+	mut virtual_time: ResMut<Time<Virtual>>,
+	mut drawings: ResMut<ScriptDrawings>,
+	mut plots: ResMut<DebugPlots>,
+) {
+	// a faulted or shut down script won't draw a new tick to replace its last one
+	let is_stopped = runtime.session.is_none() || codepilot_code.faulted;
+	if is_stopped && !drawings.shapes.is_empty() {
+		drawings.shapes.clear();
+	}
+
+	// fixed ticks already due when the debugger paused the game still run, skip them
+	if debugger.is_paused() {
+		return;
+	}
+
+	if let Ok((velocity, transform, ship, children)) = query.get_single() {
+
+		let heading_vec = transform.rotation * Vec3::X;
+		let heading_angle = transform.rotation.mul_vec3(Vec3::X).y.atan2(transform.rotation.mul_vec3(Vec3::X).x);
+
+		// Convert all enemy velocity and positions to lists
+		let enemy_velocities: PyAccessibleV3Vec = PyAccessibleV3Vec(
+			enemy_query.iter().map(|(_, vel, ..)| Vec3::new(vel.x, vel.y, vel.omega)).collect()
+		);
+
+		let enemy_positions: PyAccessibleV3Vec = PyAccessibleV3Vec(
+			enemy_query.iter().map(|(_, _, transform, ..)| {
+				//get the enemy heading as f32 radians
+				let enemy_heading = transform.rotation.mul_vec3(Vec3::X).y.atan2(transform.rotation.mul_vec3(Vec3::X).x);
+
+				Vec3::new(transform.translation.x, transform.translation.y, enemy_heading)
+			}).collect()
+		);
+
+		// unlike the lists above, contacts keep the same id from tick to tick
+		let enemies: Vec<Contact> = enemy_query.iter().map(|(entity, vel, transform, enemy_ship, &allegiance)| {
+			let enemy_heading = transform.rotation.mul_vec3(Vec3::X).y.atan2(transform.rotation.mul_vec3(Vec3::X).x);
+
+			Contact {
+				id: entity.to_bits(),
+				position: (transform.translation.x, transform.translation.y),
+				velocity: (vel.x * BASE_SPEED, vel.y * BASE_SPEED),
+				heading: enemy_heading,
+				shields: enemy_ship.current_shields,
+				max_shields: enemy_ship.max_shields,
+				allegiance,
+			}
+		}).collect();
+
+		// Codepilot player control section, a faulted script stays suspended until resumed
+		let faulted = codepilot_code.faulted;
+		if let Some(session) = runtime.session.as_ref().filter(|_| !faulted) {
+			session.enter(|vm, scope| {
+				reset_tick_globals(scope, vm);
+
+				let status = {
+					let mut io = session.io();
+					io.commands = CommandState::default();
+					io.drawings.clear();
+					io.enemies = enemies.clone();
+					io.weapons = children.into_iter().flatten()
+						.filter_map(|&child| weapon_query.get(child).ok())
+						.map(|(weapon, &weapon_type)| WeaponStatus {
+							weapon_type,
+							current_charge: weapon.current_charge,
+							charge_rate: weapon.charge_rate,
+						})
+						.collect();
+					io.status = ShipStatus {
+						position: (transform.translation.x, transform.translation.y),
+						velocity: (velocity.x * BASE_SPEED, velocity.y * BASE_SPEED),
+						angular_velocity: velocity.omega * BASE_ROT_SPEED,
+						heading: heading_angle,
+						shields: ship.current_shields,
+						max_shields: ship.max_shields,
+						weapon_cooldown: player_state.weapon_cooldown.max(0.),
+						weapon_cooldown_max: player_state.weapon_cooldown_max,
+						emp_charge: io.weapons.iter()
+							.find(|weapon| weapon.weapon_type == WeaponType::EMP)
+							.map(|weapon| weapon.current_charge),
+						score: player_state.score,
+						time: time.elapsed_seconds_f64(),
+					};
+					io.status.clone()
+				};
+
+				scope
+					.globals
+					.set_item("status", status.into_pyobject(vm), vm);
+
+				let enemies = enemies.into_iter().map(|contact| contact.into_pyobject(vm)).collect();
+				scope
+					.globals
+					.set_item("enemies", vm.ctx.new_list(enemies).into(), vm);
+
+				let projectiles = scan_projectiles(transform, velocity, laser_query.iter())
+					.into_iter()
+					.map(|projectile| projectile.into_pyobject(vm))
+					.collect();
+				scope
+					.globals
+					.set_item("projectiles", vm.ctx.new_list(projectiles).into(), vm);
+
+				// Set the player position
+				scope
+					.globals
+					.set_item("player_position", vm.new_pyobj((
+						transform.translation.x,
+						transform.translation.y,
+						heading_vec[0],
+						heading_vec[1],
+						heading_angle
+					)), vm);
+				
+				// Set the player velocity
+				scope
+					.globals
+					.set_item("player_velocity", vm.new_pyobj((velocity.x, velocity.y, velocity.omega)), vm);
+
+				scope
+					.globals
+					.set_item("enemy_positions", vm.new_pyobj(enemy_positions), vm);
+
+				scope
+					.globals
+					.set_item("enemy_velocities", vm.new_pyobj(enemy_velocities), vm);
+
+				let _ = session.tracer().start(vm, &debugger.breakpoints);
+				let player_code_res = session.run_budgeted(vm, || session.run_tick(vm, time.delta_seconds()));
+				let tick_failed = player_code_res.is_err();
+
+				// a breakpoint was hit, pause the game to step through the tick
+				let stops = session.tracer().finish(vm);
+				if !stops.is_empty() {
+					debugger.stops = stops;
+					debugger.current = Some(0);
+					virtual_time.pause();
+				}
+
+				match player_code_res {
+					Ok(player_code_res) => { codepilot_code.py_result = None},
+					Err(err) =>  { 
+						codepilot_code.py_result = Some(err);
+					}
+				}
+
+				record_console_output(session, &mut codepilot_code.codepilot_hist, time.elapsed_seconds());
+				drawings.shapes = std::mem::take(&mut session.io().drawings);
+
+				let now = time.elapsed_seconds_f64();
+				plots.record(plots::SPEED_CHANNEL, now, (Vec2::new(velocity.x, velocity.y).length() * BASE_SPEED) as f64);
+				plots.record(plots::HEADING_CHANNEL, now, heading_angle as f64);
+				plots.record(plots::SHIELDS_CHANNEL, now, ship.current_shields as f64);
+				codepilot_code.memory = session.snapshot_memory(vm);
+
+				let globals = session.snapshot_globals(vm);
+				codepilot_code.watch_globals = watch_values(&codepilot_code.watch_globals, globals);
+				let pins = codepilot_code.watch_expressions.iter()
+					.zip(session.evaluate_watches(vm, &codepilot_code.watch_expressions))
+					.map(|(expression, result)| match result {
+						Ok((type_name, value)) => (expression.clone(), type_name, value),
+						Err(err) => (expression.clone(), "error".to_owned(), err),
+					})
+					.collect();
+				codepilot_code.watch_pins = watch_values(&codepilot_code.watch_pins, pins);
+
+				// an aborted tick doesn't get to act on whatever it set before running out of time
+				if session.overran_budget() {
+					latched_commands.0 = CommandState::default();
+					return;
+				}
+
+				// same for a tick that raised, rather than flying on half the commands
+				if tick_failed {
+					latched_commands.0 = CommandState::default();
+					record_failed_tick(&mut codepilot_code);
+					return;
+				}
+				codepilot_code.failed_ticks = 0;
+
+				// commands from the codepilot module, plus the older magic globals
+				let module_commands = session.io().commands.clone();
+				let legacy_axis = |positive: &str, negative: &str| {
+					try_boolean_python_action(positive, &scope, vm) as i32 as f32
+						- try_boolean_python_action(negative, &scope, vm) as i32 as f32
+				};
+				let command_state = CommandState {
+					fire: module_commands.fire || try_boolean_python_action("fire", &scope, vm),
+					fire_weapons: module_commands.fire_weapons,
+					throttle: (module_commands.throttle + legacy_axis("forward", "backward")).clamp(-1., 1.),
+					turn: (module_commands.turn + legacy_axis("counterclockwise", "clockwise")).clamp(-1., 1.),
+				};
+
+				latched_commands.0 = command_state.clone();
+
+				let time = time.elapsed_seconds();
+
+				let last_debug_messages = get_last_debug_message_list(&codepilot_code.codepilot_hist);
+				// if let Some(last_debug_message) = last_debug_message {
+				// 	if last_debug_message != PyDebugMessage::KeyLessDebug {
+				// 		codepilot_code.codepilot_hist.push((time, CodePilotOutput::PyDebugMessage(last_debug_message)));
+				// 	}
+				// }
+				
+				let debug_list: Result<PyObjectRef, vm::prelude::PyRef<vm::builtins::PyBaseException>> = scope.globals.get_item("debug_list", vm);
+
+				let mut next_debug_messages: Vec<PyDebugMessage> = Vec::new();
+
+				if let Ok(debug_list) = debug_list {
+					let seq = debug_list.to_sequence(vm);
+					let seq_len = seq.length(vm);
+
+					if let Ok(seq_len) = seq_len {
+						for i in 0..seq_len {
+							let item = seq.get_item(i as isize, vm).unwrap();
+							let tuple =  item.to_sequence(vm);
+			
+							let key = tuple.get_item(0, vm).unwrap().str(vm).unwrap().to_string();
+							let value = tuple.get_item(1, vm).unwrap();
+
+							// numbers are plotted as well as printed
+							if key != "KeylessDebug_" && (value.payload_is::<PyFloat>() || value.payload_is::<PyInt>()) {
+								if let Ok(number) = value.clone().try_into_value::<f64>(vm) {
+									plots.record(&key, time as f64, number);
+								}
+							}
+
+							let value = value.str(vm).unwrap().to_string();
+
+							if key == "KeylessDebug_" {
+								next_debug_messages.push(PyDebugMessage::KeyLessDebug(value));
+							} else {
+								let message_matches = if let Some(ref last_debug_messages) = last_debug_messages {
+									last_debug_messages.iter().any(|m| {
+										match m {
+											PyDebugMessage::KeyedDebug(kd) => {
+												kd.key == key && kd.value == value
+											},
+											_ => false
+										}
+									})
+								} else {
+									false
+								};
+								
+								next_debug_messages.push(PyDebugMessage::KeyedDebug(KeyedDebug {
+									key,
+									value,
+									has_changed: !message_matches
+								}));									
+							}
+						}
+					}
+				}
+
+				if next_debug_messages.len() > 0 {
+					codepilot_code.codepilot_hist.push((time, CodePilotOutput::DebugMessages(next_debug_messages)));
+				}
+
+				let last_command_state = get_last_command_state(&codepilot_code.codepilot_hist);
+				if let Some(last_command_state) = last_command_state {
+					if last_command_state != command_state {
+						codepilot_code.codepilot_hist.push((time, CodePilotOutput::CommandState(command_state)));
+					}
+				} else {
+					codepilot_code.codepilot_hist.push((time, CodePilotOutput::CommandState(command_state)));
+				};
+
+
+			});
+		}
+	}
+
+	shut_down_if_over_budget(&mut runtime, &mut codepilot_code);
+}
+
+// applies the latched commands every frame until the next tick replaces them
+fn codepilot_actuation_system(
+	mut commands: Commands,
+	latched_commands: Res<LatchedCommands>,
+	runtime: NonSend<CodePilotRuntime>,
+	game_textures: Res<GameTextures>,
+	mut player_state: ResMut<PlayerState>,
+	mut fire_weapon_event: EventWriter<FireWeaponEvent>,
+	time: Res<Time>,
+	mut query: Query<(Entity, &mut Velocity, &Transform), With<Player>>,
+) {
+	if runtime.session.is_none() {
+		return;
+	}
+
+	// the accelerations are tuned per frame at the reference frame rate, scale them so
+	// the ship handles the same at any frame rate
+	let frame_scale = time.delta_seconds() * REFERENCE_FRAME_RATE;
+
+	let acceleration = 0.05 * frame_scale;
+	let ang_acceleration = 0.005 * frame_scale;
+
+	let max_speed = 2.0;
+	let max_ang_velocity = 0.5;
+
+	if let Ok((player_entity, mut velocity, transform)) = query.get_single_mut() {
+		let command_state = &latched_commands.0;
+
+		let heading_vec = transform.rotation * Vec3::X;
+		let heading_perp = transform.rotation * Vec3::Y;
+		let speed = velocity.y.hypot(velocity.x);
+		let course = (velocity.y).atan2(velocity.x);
+
+		// ensure speed is not greater than max speed
+		if speed > max_speed {
+			velocity.x = course.cos() * max_speed;
+			velocity.y = course.sin() * max_speed;
+		}
+
+		if command_state.fire {
+			try_fire_weapon(&mut commands, &game_textures, &mut player_state, transform);
+		}
+
+		// the weapon listeners skip weapons that aren't charged yet
+		for &weapon_type in &command_state.fire_weapons {
+			fire_weapon_event.send(FireWeaponEvent {
+				weapon_type,
+				weapon_alignment: Allegiance::Friendly,
+				firing_entity: player_entity,
+			});
+		}
+
+		if command_state.turn > 0. {
+			accelerate_counter_clockwise(
+				&mut velocity, transform, ang_acceleration, command_state.turn, max_ang_velocity, heading_vec, heading_perp, &mut commands);
+		}
+
+		if command_state.turn < 0. {
+			accelerate_clockwise(
+				&mut velocity, transform, ang_acceleration, -command_state.turn, max_ang_velocity, heading_vec, heading_perp, &mut commands);
+		}
+
+		if command_state.throttle > 0. {
+			accelerate_forward(
+				&mut velocity, transform, acceleration, command_state.throttle, max_speed, heading_vec, heading_perp, &mut commands
+			);
+		}
+
+		if command_state.throttle < 0. {
+			accelerate_backward(
+				&mut velocity, transform, acceleration, -command_state.throttle, max_speed, heading_vec, heading_perp, &mut commands
+			);
+		}
+	}
+}
+
+// keeps the fixed timestep in step with the configured tick rate
+fn codepilot_tick_rate_system(
+	settings: Res<CodePilotSettings>,
+	mut fixed_time: ResMut<Time<Fixed>>,
+) {
+	if settings.is_changed() {
+		fixed_time.set_timestep_hz(settings.tick_rate);
+	}
+}
+
+// moves what the script printed into the history, collapsing repeated lines
+fn record_console_output(session: &ScriptSession, codepilot_hist: &mut CodePilotHist, time: f32) {
+	let lines = session.take_console_lines();
+	let dropped = lines.len().saturating_sub(CODEPILOT_CONSOLE_LINES_PER_TICK);
+
+	for (is_error, text) in lines.into_iter().take(CODEPILOT_CONSOLE_LINES_PER_TICK) {
+		if codepilot_hist.repeat_console_line(time, &text, is_error) {
+			continue;
+		}
+
+		codepilot_hist.push((time, CodePilotOutput::Console(ConsoleLine { text, is_error, repeats: 1 })));
+	}
+
+	if dropped > 0 {
+		codepilot_hist.push((time, CodePilotOutput::Console(ConsoleLine {
+			text: format!("... {dropped} more lines not shown"),
+			is_error: true,
+			repeats: 1,
+		})));
+	}
+}
+
+// (name, type, value) as shown in the watch panel, flagging the ones that differ from the previous tick
+fn watch_values(previous: &[WatchValue], current: Vec<(String, String, String)>) -> Vec<WatchValue> {
+	current.into_iter()
+		.map(|(name, type_name, value)| {
+			let has_changed = !previous.iter().any(|watch| watch.name == name && watch.value == value);
+			WatchValue { name, type_name, value, has_changed }
+		})
+		.collect()
+}
+
+// faults codepilot once the script has raised on too many ticks in a row
+fn record_failed_tick(codepilot_code: &mut CodePilotCode) {
+	codepilot_code.failed_ticks += 1;
+
+	if codepilot_code.failed_ticks < CODEPILOT_MAX_FAILED_TICKS {
+		return;
+	}
+
+	codepilot_code.faulted = true;
+
+	let last_error = codepilot_code.py_result.take().unwrap_or_default();
+	codepilot_code.py_result = Some(format!(
+		"{last_error}\nCodepilot faulted after {} failing ticks, press {:?} to resume",
+		codepilot_code.failed_ticks,
+		CODEPILOT_RESUME_KEY
+	));
+}
+
+fn debugger_running(debugger: Res<ScriptDebugger>) -> bool {
+	!debugger.is_paused()
+}
+
+// steps through the stops recorded on a breakpoint, the game resumes after the last one
+fn codepilot_debugger_system(
+	mut debugger_events: EventReader<DebuggerActionEvent>,
+	mut compiled_events: EventReader<ScriptCompiledEvent>,
+	mut debugger: ResMut<ScriptDebugger>,
+	mut latched_commands: ResMut<LatchedCommands>,
+	mut virtual_time: ResMut<Time<Virtual>>,
+) {
+	let was_paused = debugger.is_paused();
+
+	for ev in debugger_events.read() {
+		match ev {
+			DebuggerActionEvent::Step => {
+				debugger.step();
+			}
+			DebuggerActionEvent::Continue => {
+				debugger.continue_to_breakpoint();
+			}
+			// the paused tick's commands are dropped
+			DebuggerActionEvent::Abort => {
+				latched_commands.0 = CommandState::default();
+				debugger.clear_stops();
+			}
+		}
+	}
+
+	// the stops belong to the code that was replaced
+	if compiled_events.read().count() > 0 {
+		debugger.clear_stops();
+	}
+
+	if was_paused && !debugger.is_paused() {
+		debugger.clear_stops();
+		virtual_time.unpause();
+	}
+}
+
+fn codepilot_clear_memory_system(
+	mut clear_memory_events: EventReader<ClearMemoryEvent>,
+	mut codepilot_code: ResMut<CodePilotCode>,
+	runtime: NonSend<CodePilotRuntime>,
+) {
+	if clear_memory_events.read().count() == 0 {
+		return;
+	}
+
+	if let Some(session) = runtime.session.as_ref() {
+		session.enter(|vm, _| session.clear_memory(vm));
+	}
+	codepilot_code.memory = Vec::new();
+}
+
+fn codepilot_resume_system(
+	kb: Res<Input<KeyCode>>,
+	mut codepilot_code: ResMut<CodePilotCode>,
+) {
+	if codepilot_code.faulted && kb.just_pressed(CODEPILOT_RESUME_KEY) {
+		info!("Resuming codepilot");
+		codepilot_code.faulted = false;
+		codepilot_code.failed_ticks = 0;
+		codepilot_code.py_result = None;
+	}
+}
+
+// disables codepilot once the script keeps running past its budget
+fn shut_down_if_over_budget(runtime: &mut CodePilotRuntime, codepilot_code: &mut CodePilotCode) {
+	if !runtime.session.as_ref().is_some_and(|session| session.exceeded_max_overruns()) {
+		return;
+	}
+
+	runtime.session = None;
+	codepilot_code.compiled = None;
+
+	let last_error = codepilot_code.py_result.take().unwrap_or_default();
+	codepilot_code.py_result = Some(format!("{last_error}\nCodepilot disabled: the script repeatedly exceeded its budget"));
+}
+
+// calls the script's lifecycle hooks for the ECS events that happened this frame
+fn codepilot_lifecycle_system(
+	mut codepilot_code: ResMut<CodePilotCode>,
+	mut runtime: NonSendMut<CodePilotRuntime>,
+	mut spawned_events: EventReader<PlayerSpawnedEvent>,
+	mut destroyed_events: EventReader<PlayerDestroyedEvent>,
+	mut hit_events: EventReader<ShipHitEvent>,
+	settings: Res<CodePilotSettings>,
+	fixed_time: Res<Time<Fixed>>, // the clock the tick stamps the history with, so it stays in order
+	player_query: Query<Entity, With<Player>>,
+) {
+	let Some(session) = runtime.session.as_ref().filter(|_| !codepilot_code.faulted) else {
+		spawned_events.clear();
+		destroyed_events.clear();
+		hit_events.clear();
+		return;
+	};
+
+	session.enter(|vm, _| {
+		let mut hook_results = Vec::new();
+
+		for _ in spawned_events.read() {
+			if !settings.keep_memory_on_respawn {
+				session.clear_memory(vm);
+			}
+			hook_results.extend(session.call_hook_budgeted(vm, LifecycleHook::Spawn, ()));
+		}
+
+		for ev in hit_events.read() {
+			if player_query.get(ev.ship).is_ok() {
+				hook_results.extend(session.call_hook_budgeted(vm, LifecycleHook::Hit, (ev.damage, ev.weapon_type.name())));
+			}
+		}
+
+		for _ in destroyed_events.read() {
+			hook_results.extend(session.call_hook_budgeted(vm, LifecycleHook::Death, ()));
+		}
+
+		for result in hook_results {
+			if let Err(err) = result {
+				codepilot_code.py_result = Some(err);
+			}
+		}
+
+		record_console_output(session, &mut codepilot_code.codepilot_hist, fixed_time.elapsed_seconds());
+	});
+
+	shut_down_if_over_budget(&mut runtime, &mut codepilot_code);
+}
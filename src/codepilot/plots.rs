@@ -0,0 +1,61 @@
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+use bevy::prelude::*;
+
+// Samples kept per key, the oldest are dropped first
+const PLOT_HISTORY_LEN: usize = 4000;
+const DEFAULT_PLOT_WINDOW: f64 = 10.; // seconds
+
+// Recorded every tick without the script having to dbg() them
+pub const SPEED_CHANNEL: &str = "ship.speed";
+pub const HEADING_CHANNEL: &str = "ship.heading";
+pub const SHIELDS_CHANNEL: &str = "ship.shields";
+
+/// Resource - numeric `dbg(key, value)` values and the built in ship channels over time,
+/// for the plots in the editor panel
+#[derive(Resource)]
+pub struct DebugPlots {
+	pub series: BTreeMap<String, VecDeque<[f64; 2]>>, // key -> (time, value)
+	pub selected: BTreeSet<String>,
+	pub window: f64, // seconds of history shown
+	pub paused: bool, // nothing is recorded while paused, so the plot holds still
+}
+
+impl Default for DebugPlots {
+	fn default() -> Self {
+		Self {
+			series: BTreeMap::new(),
+			selected: BTreeSet::from([SPEED_CHANNEL.to_owned()]),
+			window: DEFAULT_PLOT_WINDOW,
+			paused: false,
+		}
+	}
+}
+
+impl DebugPlots {
+	pub fn record(&mut self, key: &str, time: f64, value: f64) {
+		if self.paused || !value.is_finite() {
+			return;
+		}
+
+		let samples = self.series.entry(key.to_owned()).or_default();
+		if samples.len() >= PLOT_HISTORY_LEN {
+			samples.pop_front();
+		}
+		samples.push_back([time, value]);
+	}
+
+	// the samples of a key within the window, ending at the newest sample of any key
+	pub fn visible_samples(&self, key: &str) -> Vec<[f64; 2]> {
+		let Some(samples) = self.series.get(key) else {
+			return Vec::new();
+		};
+
+		let latest = self.series.values().filter_map(|samples| samples.back()).map(|[time, _]| *time).fold(f64::MIN, f64::max);
+		samples.iter().filter(|[time, _]| *time >= latest - self.window).copied().collect()
+	}
+
+	pub fn clear(&mut self) {
+		self.series.clear();
+	}
+}
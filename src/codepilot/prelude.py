@@ -0,0 +1,210 @@
+# Helpers loaded into every script, and the project modules it imports, before they run.
+# Only defines names, it reads the `status` and `enemies` globals when called.
+import math
+
+
+class Vec2:
+    __slots__ = ("x", "y")
+
+    def __init__(self, x=0.0, y=0.0):
+        self.x = float(x)
+        self.y = float(y)
+
+    @staticmethod
+    def of(value):
+        """Vec2 from a Vec2 or an (x, y) pair"""
+        if isinstance(value, Vec2):
+            return value
+        x, y = value
+        return Vec2(x, y)
+
+    @staticmethod
+    def from_angle(angle, length=1.0):
+        """Vec2 pointing at `angle` radians, counter-clockwise from +x"""
+        return Vec2(math.cos(angle) * length, math.sin(angle) * length)
+
+    def __add__(self, other):
+        other = Vec2.of(other)
+        return Vec2(self.x + other.x, self.y + other.y)
+
+    __radd__ = __add__
+
+    def __sub__(self, other):
+        other = Vec2.of(other)
+        return Vec2(self.x - other.x, self.y - other.y)
+
+    def __rsub__(self, other):
+        return Vec2.of(other) - self
+
+    def __mul__(self, scalar):
+        return Vec2(self.x * scalar, self.y * scalar)
+
+    __rmul__ = __mul__
+
+    def __truediv__(self, scalar):
+        return Vec2(self.x / scalar, self.y / scalar)
+
+    def __neg__(self):
+        return Vec2(-self.x, -self.y)
+
+    def __eq__(self, other):
+        try:
+            other = Vec2.of(other)
+        except (TypeError, ValueError):
+            return NotImplemented
+        return self.x == other.x and self.y == other.y
+
+    def __hash__(self):
+        return hash((self.x, self.y))
+
+    def __iter__(self):
+        yield self.x
+        yield self.y
+
+    def __len__(self):
+        return 2
+
+    def __getitem__(self, index):
+        return (self.x, self.y)[index]
+
+    def __repr__(self):
+        return f"Vec2({self.x:.2f}, {self.y:.2f})"
+
+    def dot(self, other):
+        other = Vec2.of(other)
+        return self.x * other.x + self.y * other.y
+
+    def cross(self, other):
+        """z of the 3d cross product, positive when `other` is counter-clockwise of this"""
+        other = Vec2.of(other)
+        return self.x * other.y - self.y * other.x
+
+    def length(self):
+        return math.hypot(self.x, self.y)
+
+    def length_squared(self):
+        return self.x * self.x + self.y * self.y
+
+    def normalized(self):
+        """Unit vector in the same direction, the zero vector stays zero"""
+        length = self.length()
+        if length == 0:
+            return Vec2()
+        return self / length
+
+    def angle(self):
+        """Radians counter-clockwise from +x, in [-pi, pi]"""
+        return math.atan2(self.y, self.x)
+
+    def rotated(self, angle):
+        cos, sin = math.cos(angle), math.sin(angle)
+        return Vec2(self.x * cos - self.y * sin, self.x * sin + self.y * cos)
+
+    def distance_to(self, other):
+        return (Vec2.of(other) - self).length()
+
+
+def wrap_angle(angle):
+    """The same angle in [-pi, pi]"""
+    return (angle + math.pi) % math.tau - math.pi
+
+
+class Ship:
+    """The player's ship as of this tick. Built from `status` unless given one"""
+
+    def __init__(self, status=None):
+        if status is None:
+            status = globals()["status"]
+        self.position = Vec2.of(status.position)
+        self.velocity = Vec2.of(status.velocity)
+        self.angular_velocity = status.angular_velocity
+        self.heading = status.heading
+        self.shields = status.shields
+        self.max_shields = status.max_shields
+
+    @property
+    def heading_vector(self):
+        return Vec2.from_angle(self.heading)
+
+    @property
+    def shield_fraction(self):
+        if self.max_shields <= 0:
+            return 0.0
+        return self.shields / self.max_shields
+
+    def bearing_to(self, point):
+        """Radians to turn to face `point`, positive is counter-clockwise"""
+        return wrap_angle((Vec2.of(point) - self.position).angle() - self.heading)
+
+    def enemies(self, contacts=None):
+        """Enemy ships in sensor range, nearest first"""
+        if contacts is None:
+            contacts = globals()["enemies"]
+        seen = [Enemy(contact, self) for contact in contacts if contact.allegiance == "enemy"]
+        return sorted(seen, key=lambda enemy: enemy.distance)
+
+    def nearest_enemy(self, contacts=None):
+        seen = self.enemies(contacts)
+        return seen[0] if seen else None
+
+    def __repr__(self):
+        return f"Ship(position={self.position}, heading={self.heading:.2f}, shields={self.shields:.0f})"
+
+
+class Enemy:
+    """An enemy contact, measured from the ship"""
+
+    def __init__(self, contact, ship):
+        self.id = contact.id
+        self.position = Vec2.of(contact.position)
+        self.velocity = Vec2.of(contact.velocity)
+        self.heading = contact.heading
+        self.shields = contact.shields
+        self.max_shields = contact.max_shields
+        self.ship = ship
+
+    @property
+    def offset(self):
+        """From the ship to the enemy"""
+        return self.position - self.ship.position
+
+    @property
+    def distance(self):
+        return self.offset.length()
+
+    @property
+    def bearing(self):
+        """Radians the ship has to turn to face the enemy, positive is counter-clockwise"""
+        return self.ship.bearing_to(self.position)
+
+    @property
+    def relative_velocity(self):
+        """The enemy's velocity as seen from the ship"""
+        return self.velocity - self.ship.velocity
+
+    @property
+    def closing_speed(self):
+        """How fast the distance is shrinking, negative when moving apart"""
+        offset = self.offset
+        if offset.length_squared() == 0:
+            return 0.0
+        return -self.relative_velocity.dot(offset.normalized())
+
+    def intercept(self):
+        """Where to aim the laser to hit this enemy, see `codepilot.intercept`"""
+        from codepilot import intercept
+        return intercept(self.id)
+
+    def __repr__(self):
+        return f"Enemy(id={self.id}, distance={self.distance:.0f}, bearing={self.bearing:.2f})"
+
+
+debug_list = []
+def dbg(key = None, value = None):
+    if value is None:
+        return
+
+    if key is None:
+        debug_list.append(("KeylessDebug_", value))
+    else:
+        debug_list.append((key, value))
@@ -0,0 +1,86 @@
+use std::collections::{BTreeMap, HashMap};
+
+use rustpython_vm as vm;
+use vm::{builtins::{PyCode, PyStr}, function::FuncArgs, scope::Scope, PyObjectRef, PyRef, PyResult, VirtualMachine};
+
+use super::runtime::exception_to_string;
+
+/// Makes the other files of the project importable from the script and from each other.
+/// Every module is compiled up front so syntax errors show up on compile, and runs the
+/// first time it is imported. A file named after a module that can already be imported,
+/// like `random.py`, is refused rather than shadowing it. Modules start out with the names
+/// defined by the prelude, as the script does.
+pub fn install_import_hook(vm: &VirtualMachine, modules: &BTreeMap<String, String>, prelude: Vec<(PyObjectRef, PyObjectRef)>) -> Result<(), String> {
+	let mut compiled: HashMap<String, PyRef<PyCode>> = HashMap::new();
+
+	for (name, source) in modules {
+		if is_importable(vm, name).map_err(|exc| exception_to_string(vm, &exc))? {
+			return Err(format!("{name}.py has the same name as an existing module, rename it"));
+		}
+
+		let code = vm
+			.compile(source, vm::compiler::Mode::Exec, format!("{name}.py"))
+			.map_err(|err| exception_to_string(vm, &vm.new_syntax_error(&err, Some(source))))?;
+
+		compiled.insert(name.clone(), code);
+	}
+
+	let import = vm.builtins.get_attr("__import__", vm).map_err(|exc| exception_to_string(vm, &exc))?;
+
+	let project_import = vm.new_function("__import__", move |args: FuncArgs, vm: &VirtualMachine| -> PyResult {
+		let module = args.args.first().and_then(|module| module.payload::<PyStr>());
+
+		// relative imports never name a project module
+		if let (Some(module), 0) = (module, import_level(&args, vm)?) {
+			if let Some(code) = compiled.get(module.as_str()) {
+				return load_module(vm, module.as_str(), code, &prelude);
+			}
+		}
+
+		import.call(args, vm)
+	});
+
+	vm.builtins.set_attr("__import__", project_import, vm).map_err(|exc| exception_to_string(vm, &exc))
+}
+
+// found by the regular import machinery, the stdlib or anything else on sys.path
+fn is_importable(vm: &VirtualMachine, name: &str) -> PyResult<bool> {
+	let find_spec = vm.import("importlib.util", None, 0)?
+		.get_attr("util", vm)?
+		.get_attr("find_spec", vm)?;
+
+	Ok(!vm.is_none(&find_spec.call((name,), vm)?))
+}
+
+// __import__(name, globals=None, locals=None, fromlist=(), level=0)
+fn import_level(args: &FuncArgs, vm: &VirtualMachine) -> PyResult<usize> {
+	match args.args.get(4).or_else(|| args.kwargs.get("level")) {
+		Some(level) => level.try_to_value(vm),
+		None => Ok(0),
+	}
+}
+
+fn load_module(vm: &VirtualMachine, name: &str, code: &PyRef<PyCode>, prelude: &[(PyObjectRef, PyObjectRef)]) -> PyResult {
+	let sys_modules = vm.sys_module.get_attr("modules", vm)?;
+
+	if let Ok(module) = sys_modules.get_item(name, vm) {
+		return Ok(module);
+	}
+
+	let attrs = vm.ctx.new_dict();
+	for (prelude_name, value) in prelude {
+		attrs.set_item(&**prelude_name, value.clone(), vm)?;
+	}
+	attrs.set_item("__name__", vm.new_pyobj(name), vm)?;
+	let module: PyObjectRef = vm.new_module(name, attrs.clone(), None).into();
+
+	// registered before running so modules can import each other
+	sys_modules.set_item(name, module.clone(), vm)?;
+
+	if let Err(exc) = vm.run_code_obj(code.clone(), Scope::with_builtins(None, attrs, vm)) {
+		let _ = sys_modules.del_item(name, vm);
+		return Err(exc);
+	}
+
+	Ok(module)
+}
@@ -0,0 +1,420 @@
+use std::{cell::{Cell, RefCell}, collections::{BTreeMap, HashSet}, sync::{Arc, Mutex, MutexGuard}, time::Duration};
+
+use rustpython_vm as vm;
+use vm::{builtins::{PyBaseExceptionRef, PyCode, PyDict, PyDictRef, PyModule, PyStr}, function::IntoFuncArgs, scope::Scope, AsObject, Interpreter, PyPayload, PyRef, PyResult, VirtualMachine};
+
+use super::{api::{codepilot, ScriptIo, ScriptIoRef}, debugger::{self, Tracer}, project, sandbox::{self, SandboxPolicy, SCRIPT_FILE_NAME, SCRIPT_MODULE_NAME}, watchdog::{Watchdog, BUDGET_EXCEEDED_MESSAGE}};
+
+// Wall clock time a script may take per tick (or per lifecycle hook) before it is aborted
+const TICK_BUDGET: Duration = Duration::from_millis(50);
+// Consecutive budget overruns before the session is shut down
+const MAX_BUDGET_OVERRUNS: u32 = 3;
+
+// Global dict scripts can keep state in, see `ScriptSession::snapshot_memory`
+const MEMORY_GLOBAL: &str = "memory";
+
+// `DebugDraw` methods exported as functions of the codepilot module
+const DRAW_FUNCTIONS: [&str; 4] = ["draw_line", "draw_circle", "draw_arrow", "draw_text"];
+
+// Globals set by the runtime or the prelude rather than the script, left out of the watch panel
+pub const PROVIDED_GLOBALS: [&str; 11] = [
+	"status", "enemies", "projectiles", "player_position", "player_velocity", "enemy_positions",
+	"enemy_velocities", "dt", "tick", "debug_list", MEMORY_GLOBAL,
+];
+
+// Globals the script sets to issue commands, cleared before every tick so a command
+// only lasts for the tick that set it
+const COMMAND_GLOBALS: [&str; 5] = ["fire", "forward", "backward", "clockwise", "counterclockwise"];
+
+/// NonSend Resource - the long lived python runtime for the player script.
+/// The interpreter isn't thread safe, so this lives on the main thread.
+#[derive(Default)]
+pub struct CodePilotRuntime {
+	pub session: Option<ScriptSession>,
+}
+
+/// Functions a script can define to be called by the runtime when the matching event happens
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum LifecycleHook {
+	Start,
+	Tick,
+	Spawn,
+	Death,
+	Hit,
+}
+
+impl LifecycleHook {
+	const ALL: [LifecycleHook; 5] = [
+		LifecycleHook::Start,
+		LifecycleHook::Tick,
+		LifecycleHook::Spawn,
+		LifecycleHook::Death,
+		LifecycleHook::Hit,
+	];
+
+	pub fn function_name(&self) -> &'static str {
+		match self {
+			LifecycleHook::Start => "on_start",
+			LifecycleHook::Tick => "on_tick",
+			LifecycleHook::Spawn => "on_spawn",
+			LifecycleHook::Death => "on_death",
+			LifecycleHook::Hit => "on_hit",
+		}
+	}
+}
+
+/// A compiled script together with the interpreter and scope it runs in.
+/// Created when a `CompileCodeEvent` is handled and reused every tick, so module level
+/// state survives between ticks.
+pub struct ScriptSession {
+	interpreter: Interpreter,
+	scope: Scope,
+	code: PyRef<PyCode>,
+	io: ScriptIoRef,
+	hooks: RefCell<HashSet<LifecycleHook>>, // found once the module body has run
+	started: Cell<bool>, // module body has run at least once
+	start_called: Cell<bool>, // on_start() has been called, after the first body run that succeeded
+	ticks: Cell<u64>,
+	watchdog: Watchdog,
+	overruns: Cell<u32>, // consecutive runs that exceeded the budget
+	tracer: Tracer,
+}
+
+impl ScriptSession {
+	/// Compiles the script along with the project modules it can import, by module name
+	pub fn compile(source: &str, modules: &BTreeMap<String, String>, sandbox_policy: &SandboxPolicy) -> Result<Self, String> {
+		let project_modules: Vec<String> = modules.keys().cloned().collect();
+
+		if sandbox_policy.enabled {
+			sandbox::check_source(source, sandbox_policy, &project_modules)?;
+
+			for (name, module_source) in modules {
+				sandbox::check_source(module_source, sandbox_policy, &project_modules)
+					.map_err(|err| format!("{name}.py: {err}"))?;
+			}
+		}
+
+		let (watchdog, signal_receiver) = Watchdog::new();
+		let io: ScriptIoRef = Arc::new(Mutex::new(ScriptIo::default()));
+
+		let interpreter = rustpython::InterpreterConfig::new()
+			.init_stdlib()
+			.init_hook(Box::new(move |vm| {
+				vm.set_user_signal_channel(signal_receiver);
+				vm.add_native_module("codepilot".to_owned(), Box::new(codepilot::make_module));
+			}))
+			.interpreter();
+
+		let compiled = interpreter.enter(|vm| -> Result<(Scope, PyRef<PyCode>), String> {
+			let scope = vm.new_scope_with_builtins();
+			scope.globals.set_item("__name__", vm.new_pyobj(SCRIPT_MODULE_NAME), vm)
+				.and_then(|_| scope.globals.set_item(MEMORY_GLOBAL, vm.ctx.new_dict().into(), vm))
+				.map_err(|exc| exception_to_string(vm, &exc))?;
+
+			// scripts control the ship through `from codepilot import ship`
+			let controller = codepilot::ShipController { io: io.clone() }.into_pyobject(vm);
+			let draw = codepilot::DebugDraw { io: io.clone() }.into_pyobject(vm);
+			vm.import("codepilot", None, 0)
+				.and_then(|module| {
+					module.set_attr("intercept", controller.get_attr("intercept", vm)?, vm)?;
+					for name in DRAW_FUNCTIONS {
+						module.set_attr(name, draw.get_attr(name, vm)?, vm)?;
+					}
+					module.set_attr("ship", controller, vm)
+				})
+				.map_err(|exc| exception_to_string(vm, &exc))?;
+
+			// print output goes to the console panel instead of the terminal
+			for (stream, is_error) in [("stdout", false), ("stderr", true)] {
+				let writer = codepilot::ConsoleWriter { io: io.clone(), is_error };
+				vm.sys_module.set_attr(stream, writer.into_pyobject(vm), vm)
+					.map_err(|exc| exception_to_string(vm, &exc))?;
+			}
+
+			// Vec2, Ship, Enemy and dbg. The prelude only defines names so it's loaded once per session,
+			// before the project files and the sandbox so its own imports can't resolve to either
+			let before_prelude: HashSet<String> = scope.globals.clone().into_iter()
+				.filter_map(|(name, _)| Some(name.payload::<PyStr>()?.as_str().to_owned()))
+				.collect();
+			let prelude_code = vm::py_compile!(file = "./src/codepilot/prelude.py");
+			vm.run_code_obj(vm.ctx.new_code(prelude_code), scope.clone())
+				.map_err(|exc| exception_to_string(vm, &exc))?;
+
+			// the same objects, so `Ship()` and `dbg()` in a project module still use the script's globals
+			let prelude = scope.globals.clone().into_iter()
+				.filter(|(name, value)| {
+					name.payload::<PyStr>().is_some_and(|name| {
+						!before_prelude.contains(name.as_str()) && !PROVIDED_GLOBALS.contains(&name.as_str())
+					}) && !value.payload_is::<PyModule>()
+				})
+				.collect::<Vec<_>>();
+
+			project::install_import_hook(vm, modules, prelude)?;
+
+			// installed last so it also guards imports of the project modules
+			if sandbox_policy.enabled {
+				sandbox::install(vm, sandbox_policy, &project_modules)
+					.and_then(|_| sandbox::proxy_globals(&scope.globals, vm))
+					.map_err(|exc| exception_to_string(vm, &exc))?;
+			}
+
+			let code = vm
+				.compile(source, vm::compiler::Mode::Exec, SCRIPT_FILE_NAME.to_owned())
+				.map_err(|err| exception_to_string(vm, &vm.new_syntax_error(&err, Some(source))))?;
+
+			Ok((scope, code))
+		});
+
+		let tracer = Tracer::new(&project_modules, watchdog.pauser());
+		compiled.map(|(scope, code)| Self {
+			interpreter,
+			scope,
+			code,
+			io,
+			hooks: RefCell::new(HashSet::new()),
+			started: Cell::new(false),
+			start_called: Cell::new(false),
+			ticks: Cell::new(0),
+			watchdog,
+			overruns: Cell::new(0),
+			tracer,
+		})
+	}
+
+	pub fn code(&self) -> PyRef<PyCode> {
+		self.code.clone()
+	}
+
+	pub fn enter<R>(&self, f: impl FnOnce(&VirtualMachine, &Scope) -> R) -> R {
+		self.interpreter.enter(|vm| f(vm, &self.scope))
+	}
+
+	/// The lines printed since the last call, with whether each went to stderr.
+	/// A line that hasn't been ended yet is returned as is.
+	pub fn take_console_lines(&self) -> Vec<(bool, String)> {
+		// join consecutive writes to the same stream, print() writes the text and the newline separately
+		let mut runs: Vec<(bool, String)> = Vec::new();
+		for (is_error, text) in std::mem::take(&mut self.io().console) {
+			match runs.last_mut() {
+				Some((last_is_error, run)) if *last_is_error == is_error => run.push_str(&text),
+				_ => runs.push((is_error, text)),
+			}
+		}
+
+		runs.into_iter()
+			.flat_map(|(is_error, run)| run.lines().map(|line| (is_error, line.to_owned())).collect::<Vec<_>>())
+			.collect()
+	}
+
+	// the script may have replaced the dict, so it's looked up every time
+	fn memory(&self, vm: &VirtualMachine) -> Option<PyDictRef> {
+		self.scope.globals.get_item(MEMORY_GLOBAL, vm).ok()?.downcast::<PyDict>().ok()
+	}
+
+	/// The `memory` entries as `(repr(key), repr(value))`, for showing in the panel and
+	/// for carrying over to the next session. Values that aren't python literals can't be
+	/// carried over.
+	pub fn snapshot_memory(&self, vm: &VirtualMachine) -> Vec<(String, String)> {
+		let Some(memory) = self.memory(vm) else {
+			return Vec::new();
+		};
+
+		memory.into_iter()
+			.filter_map(|(key, value)| {
+				let key = key.repr(vm).ok()?;
+				let value = value.repr(vm).ok()?;
+				Some((key.as_str().to_owned(), value.as_str().to_owned()))
+			})
+			.collect()
+	}
+
+	/// Fills `memory` from a snapshot of another session.
+	/// Returns the keys that couldn't be restored.
+	pub fn restore_memory(&self, vm: &VirtualMachine, snapshot: &[(String, String)]) -> Vec<String> {
+		let literal_eval = vm.import("ast", None, 0).and_then(|ast| ast.get_attr("literal_eval", vm));
+		let (Some(memory), Ok(literal_eval)) = (self.memory(vm), literal_eval) else {
+			return snapshot.iter().map(|(key, _)| key.clone()).collect();
+		};
+
+		let mut dropped = Vec::new();
+
+		for (key, value) in snapshot {
+			let restored = literal_eval.call((key.as_str(),), vm).and_then(|restored_key| {
+				let restored_value = literal_eval.call((value.as_str(),), vm)?;
+				memory.set_item(&*restored_key, restored_value, vm)
+			});
+
+			if restored.is_err() {
+				dropped.push(key.clone());
+			}
+		}
+
+		dropped
+	}
+
+	pub fn clear_memory(&self, vm: &VirtualMachine) {
+		if let Some(memory) = self.memory(vm) {
+			let _ = vm.call_method(memory.as_object(), "clear", ());
+		}
+	}
+
+	/// The script's own globals as `(name, type, repr)`, for the watch panel
+	pub fn snapshot_globals(&self, vm: &VirtualMachine) -> Vec<(String, String, String)> {
+		let mut globals = self.scope.globals.clone().into_iter()
+			.filter_map(|(name, value)| {
+				let name = name.payload::<PyStr>()?.as_str().to_owned();
+				if PROVIDED_GLOBALS.contains(&name.as_str()) || !debugger::is_variable(&name, &value) {
+					return None;
+				}
+
+				let type_name = value.class().name().to_string();
+				Some((name, type_name, debugger::short_repr(&value, vm)?))
+			})
+			.collect::<Vec<_>>();
+
+		globals.sort();
+		globals
+	}
+
+	/// Evaluates the pinned watch expressions against the script's globals, as `(type, repr)`
+	/// or the error. Runs under the tick budget, but doesn't count as an overrun.
+	pub fn evaluate_watches(&self, vm: &VirtualMachine, expressions: &[String]) -> Vec<Result<(String, String), String>> {
+		let evaluate = |expression: &String| -> Result<(String, String), String> {
+			let code = vm
+				.compile(expression, vm::compiler::Mode::Eval, "<watch>".to_owned())
+				.map_err(|err| err.to_string())?;
+			let value = vm
+				.run_code_obj(code, self.scope.clone())
+				.map_err(|exc| exception_to_string(vm, &exc).trim_end().lines().last().unwrap_or_default().to_owned())?;
+
+			let repr = debugger::short_repr(&value, vm).unwrap_or_else(|| "<no repr>".to_owned());
+			Ok((value.class().name().to_string(), repr))
+		};
+
+		let (results, _) = self.watchdog.run(TICK_BUDGET, || expressions.iter().map(evaluate).collect());
+		results
+	}
+
+	pub fn tracer(&self) -> &Tracer {
+		&self.tracer
+	}
+
+	pub fn io(&self) -> MutexGuard<ScriptIo> {
+		self.io.lock().unwrap()
+	}
+
+	/// Runs `f` under the tick budget, turning the result into the message to show in the
+	/// editor. Consecutive overruns are counted so the caller can shut the session down.
+	pub fn run_budgeted(&self, vm: &VirtualMachine, f: impl FnOnce() -> PyResult<()>) -> Result<(), String> {
+		self.budgeted(vm, true, f)
+	}
+
+	/// Calls the hook under the tick budget, None when the script doesn't define it. An overrun
+	/// counts the same as on a tick, but a hook that returns in time doesn't reset the count,
+	/// only a tick does
+	pub fn call_hook_budgeted(&self, vm: &VirtualMachine, hook: LifecycleHook, args: impl IntoFuncArgs) -> Option<Result<(), String>> {
+		if !self.started.get() || !self.hooks.borrow().contains(&hook) {
+			return None;
+		}
+
+		Some(self.budgeted(vm, false, || self.call_hook(vm, hook, args)))
+	}
+
+	fn budgeted(&self, vm: &VirtualMachine, resets_overruns: bool, f: impl FnOnce() -> PyResult<()>) -> Result<(), String> {
+		let (result, over_budget) = self.watchdog.run(TICK_BUDGET, f);
+
+		if over_budget {
+			self.overruns.set(self.overruns.get() + 1);
+
+			return Err(format!(
+				"{BUDGET_EXCEEDED_MESSAGE}: aborted after {}ms ({} of {} allowed overruns)",
+				TICK_BUDGET.as_millis(),
+				self.overruns.get(),
+				MAX_BUDGET_OVERRUNS
+			));
+		}
+
+		if resets_overruns {
+			self.overruns.set(0);
+		}
+		result.map_err(|exc| exception_to_string(vm, &exc))
+	}
+
+	/// The last budgeted run was aborted
+	pub fn overran_budget(&self) -> bool {
+		self.overruns.get() > 0
+	}
+
+	pub fn exceeded_max_overruns(&self) -> bool {
+		self.overruns.get() >= MAX_BUDGET_OVERRUNS
+	}
+
+	/// Runs one tick of the script. The module body runs on the first tick, followed by
+	/// `on_start()`. A body that raises runs again on the next tick, and `on_start()` waits for
+	/// the first run that gets through. Scripts without an `on_tick(dt)` keep the old behaviour
+	/// of running the whole module every tick. `dt` and the `tick` counter are exposed as globals.
+	pub fn run_tick(&self, vm: &VirtualMachine, dt: f32) -> PyResult<()> {
+		let tick = self.ticks.get();
+		self.ticks.set(tick + 1);
+
+		self.scope.globals.set_item("dt", vm.new_pyobj(dt), vm)?;
+		self.scope.globals.set_item("tick", vm.new_pyobj(tick), vm)?;
+
+		if !self.start_called.get() {
+			// mark as started first so a failing body doesn't leave the hooks unreachable
+			self.started.set(true);
+			let body = vm.run_code_obj(self.code.clone(), self.scope.clone());
+
+			// hooks may be defined, imported from a project module or assigned, so they're
+			// looked up in the globals rather than the source
+			*self.hooks.borrow_mut() = self.find_hooks(vm);
+
+			body?;
+			self.start_called.set(true);
+			self.call_hook(vm, LifecycleHook::Start, ())?;
+		} else if !self.hooks.borrow().contains(&LifecycleHook::Tick) {
+			vm.run_code_obj(self.code.clone(), self.scope.clone())?;
+		}
+
+		self.call_hook(vm, LifecycleHook::Tick, (dt,))
+	}
+
+	/// Calls the hook if the script defines it. Hooks are ignored until the module body has
+	/// run, since that's what defines them.
+	fn call_hook(&self, vm: &VirtualMachine, hook: LifecycleHook, args: impl IntoFuncArgs) -> PyResult<()> {
+		if !self.started.get() || !self.hooks.borrow().contains(&hook) {
+			return Ok(());
+		}
+
+		let func = self.scope.globals.get_item(hook.function_name(), vm)?;
+		func.call(args, vm)?;
+
+		Ok(())
+	}
+
+	// the lifecycle hooks the script's globals hold a callable for
+	fn find_hooks(&self, vm: &VirtualMachine) -> HashSet<LifecycleHook> {
+		LifecycleHook::ALL.iter()
+			.filter(|hook| {
+				self.scope.globals.get_item(hook.function_name(), vm)
+					.is_ok_and(|func| func.to_callable().is_some())
+			})
+			.copied()
+			.collect()
+	}
+}
+
+// clears the output globals and debug list left over from the previous tick
+pub fn reset_tick_globals(scope: &Scope, vm: &VirtualMachine) {
+	for key in COMMAND_GLOBALS {
+		let _ = scope.globals.del_item(key, vm);
+	}
+
+	let _ = scope.globals.set_item("debug_list", vm.ctx.new_list(Vec::new()).into(), vm);
+}
+
+pub fn exception_to_string(vm: &VirtualMachine, exc: &PyBaseExceptionRef) -> String {
+	let mut s = String::new();
+	let _ = vm.write_exception(&mut s, exc);
+	s
+}
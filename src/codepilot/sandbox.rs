@@ -0,0 +1,319 @@
+use rustpython_parser::{self as parser, ast::{self, Mod, Ranged}, lexer, Tok};
+use rustpython_vm as vm;
+use vm::{builtins::{PyDict, PyDictRef, PyModule, PyStr}, function::FuncArgs, PyObjectRef, PyResult, VirtualMachine};
+
+// The name the script runs under
+pub const SCRIPT_MODULE_NAME: &str = "__main__";
+// The file name the script is compiled with. Only code compiled from the script or the project
+// files is sandboxed, the modules it imports are free to use whatever they need internally
+pub const SCRIPT_FILE_NAME: &str = "<embedded>";
+
+const DEFAULT_ALLOWED_MODULES: &str = "math, random, collections, itertools, functools";
+
+// Builtins that reach outside the game, or could run code the import checks haven't seen
+const BLOCKED_BUILTINS: [&str; 9] = ["open", "exec", "eval", "compile", "input", "breakpoint", "exit", "quit", "vars"];
+
+// Attributes that lead from any object back to module globals, the builtins or every loaded
+// class, e.g. `().__class__.__mro__[1].__subclasses__()` or `f.__globals__`, or that load a
+// module without going through `__import__`, e.g. `__builtins__.__loader__.load_module("posix")`
+const BLOCKED_ATTRIBUTES: [&str; 18] = [
+	"__subclasses__", "__globals__", "__builtins__", "__mro__", "__bases__", "__base__", "__dict__",
+	"__getattribute__", "__code__", "__closure__", "f_globals", "f_builtins", "f_back", "tb_frame", "gi_frame",
+	"__loader__", "__spec__", "load_module",
+];
+
+// Builtins that look attributes up by name, the name is their second argument
+const ATTRIBUTE_BUILTINS: [&str; 4] = ["getattr", "setattr", "delattr", "hasattr"];
+
+/// Which modules a script may import when the sandbox is enabled.
+/// Applied when the script is compiled.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SandboxPolicy {
+	pub enabled: bool,
+	pub allowed_modules: String, // comma separated, as edited in the panel
+}
+
+impl Default for SandboxPolicy {
+	fn default() -> Self {
+		Self {
+			enabled: false,
+			allowed_modules: DEFAULT_ALLOWED_MODULES.to_owned(),
+		}
+	}
+}
+
+impl SandboxPolicy {
+	pub fn allows(&self, module: &str) -> bool {
+		// `import a.b` is allowed when `a` is, and the ship api is always available
+		let top_level = module.split('.').next().unwrap_or(module);
+
+		top_level == "codepilot"
+			|| self.allowed_modules.split(',').map(str::trim).any(|allowed| !allowed.is_empty() && allowed == top_level)
+	}
+
+	fn blocked_import_message(&self, module: &str) -> String {
+		if module.is_empty() {
+			return "relative imports are not allowed in sandbox mode".to_owned();
+		}
+
+		format!("import of '{module}' is not allowed in sandbox mode (allowed: {})", self.allowed_modules)
+	}
+}
+
+/// Rejects scripts that import modules outside the allowlist or the project, or that use one
+/// of the blocked attributes.
+pub fn check_source(source: &str, policy: &SandboxPolicy, project_modules: &[String]) -> Result<(), String> {
+	check_attributes(source)?;
+	check_imports(source, policy, project_modules)
+}
+
+// Modules that are already loaded are handed out from `sys.modules` without calling
+// `__import__`, so the runtime guard alone would let them through
+fn check_imports(source: &str, policy: &SandboxPolicy, project_modules: &[String]) -> Result<(), String> {
+	// syntax errors are left for the compiler to report
+	let Ok(Mod::Module(module)) = parser::parse(source, parser::Mode::Module, SCRIPT_FILE_NAME) else {
+		return Ok(());
+	};
+
+	let mut imports = Vec::new();
+	collect_imports(&module.body, &mut imports);
+
+	match imports.into_iter().find(|(module, _)| !policy.allows(module) && !is_project_module(module, project_modules)) {
+		Some((module, offset)) => {
+			let line = source[..offset].matches('\n').count() + 1;
+			Err(format!("ImportError: line {line}: {}", policy.blocked_import_message(&module)))
+		}
+		None => Ok(()),
+	}
+}
+
+// the modules imported anywhere in the statements, with the offset of the import
+fn collect_imports(body: &[ast::Stmt], imports: &mut Vec<(String, usize)>) {
+	for stmt in body {
+		let offset = stmt.range().start().to_usize();
+
+		match stmt {
+			ast::Stmt::Import(import) => {
+				imports.extend(import.names.iter().map(|alias| (alias.name.to_string(), offset)));
+			}
+			ast::Stmt::ImportFrom(import) => {
+				// relative imports have no module
+				let module = import.module.as_ref().map_or(String::new(), |module| module.to_string());
+				imports.push((module, offset));
+			}
+			ast::Stmt::FunctionDef(def) => collect_imports(&def.body, imports),
+			ast::Stmt::AsyncFunctionDef(def) => collect_imports(&def.body, imports),
+			ast::Stmt::ClassDef(def) => collect_imports(&def.body, imports),
+			ast::Stmt::For(stmt) => {
+				collect_imports(&stmt.body, imports);
+				collect_imports(&stmt.orelse, imports);
+			}
+			ast::Stmt::AsyncFor(stmt) => {
+				collect_imports(&stmt.body, imports);
+				collect_imports(&stmt.orelse, imports);
+			}
+			ast::Stmt::While(stmt) => {
+				collect_imports(&stmt.body, imports);
+				collect_imports(&stmt.orelse, imports);
+			}
+			ast::Stmt::If(stmt) => {
+				collect_imports(&stmt.body, imports);
+				collect_imports(&stmt.orelse, imports);
+			}
+			ast::Stmt::With(stmt) => collect_imports(&stmt.body, imports),
+			ast::Stmt::AsyncWith(stmt) => collect_imports(&stmt.body, imports),
+			ast::Stmt::Match(stmt) => {
+				for case in stmt.cases.iter() {
+					collect_imports(&case.body, imports);
+				}
+			}
+			ast::Stmt::Try(stmt) => {
+				collect_imports(&stmt.body, imports);
+				for ast::ExceptHandler::ExceptHandler(handler) in stmt.handlers.iter() {
+					collect_imports(&handler.body, imports);
+				}
+				collect_imports(&stmt.orelse, imports);
+				collect_imports(&stmt.finalbody, imports);
+			}
+			ast::Stmt::TryStar(stmt) => {
+				collect_imports(&stmt.body, imports);
+				for ast::ExceptHandler::ExceptHandler(handler) in stmt.handlers.iter() {
+					collect_imports(&handler.body, imports);
+				}
+				collect_imports(&stmt.orelse, imports);
+				collect_imports(&stmt.finalbody, imports);
+			}
+			_ => {}
+		}
+	}
+}
+
+// `.name` anywhere in the source, the lexer is enough as there's no other syntax for it.
+// Lookups by a computed name go through the guarded getattr builtins instead
+fn check_attributes(source: &str) -> Result<(), String> {
+	let mut after_dot = false;
+
+	for token in lexer::lex(source, parser::Mode::Module) {
+		// syntax errors are left for the compiler to report
+		let Ok((token, range)) = token else {
+			return Ok(());
+		};
+
+		if let Tok::Name { name } = &token {
+			if after_dot && is_blocked_attribute(name) {
+				let line = source[..range.start().to_usize()].matches('\n').count() + 1;
+				return Err(format!("AttributeError: line {line}: {}", blocked_attribute_message(name)));
+			}
+		}
+
+		after_dot = matches!(token, Tok::Dot);
+	}
+
+	Ok(())
+}
+
+fn is_blocked_attribute(name: &str) -> bool {
+	BLOCKED_ATTRIBUTES.contains(&name)
+}
+
+fn blocked_attribute_message(name: &str) -> String {
+	format!("'{name}' is not available in sandbox mode")
+}
+
+fn is_project_module(module: &str, project_modules: &[String]) -> bool {
+	project_modules.iter().any(|project_module| project_module == module)
+}
+
+/// Replaces `__import__` and the blocked builtins with versions that raise an `ImportError`
+/// when called from the script or its project modules. Dynamic imports like
+/// `__import__("os")` are caught here.
+///
+/// Allowed modules are handed to the script as proxies holding only their public attributes,
+/// so `random._os` or `collections._sys` can't reach the modules they import. The getattr
+/// builtins refuse the blocked attributes the source check can't see, like `getattr(f, name)`.
+pub fn install(vm: &VirtualMachine, policy: &SandboxPolicy, project_modules: &[String]) -> PyResult<()> {
+	let import = vm.builtins.get_attr("__import__", vm)?;
+	let import_policy = policy.clone();
+
+	let import_project_modules = project_modules.to_vec();
+	let guarded_import = vm.new_function("__import__", move |args: FuncArgs, vm: &VirtualMachine| -> PyResult {
+		if called_from_script(vm, &import_project_modules) {
+			let module = args.args.first()
+				.and_then(|module| module.payload::<PyStr>())
+				.map_or(String::new(), |module| module.as_str().to_owned());
+
+			if is_project_module(&module, &import_project_modules) {
+				return import.call(args, vm);
+			}
+
+			if !import_policy.allows(&module) {
+				let message = import_policy.blocked_import_message(&module);
+				return Err(vm.new_import_error(message, vm.ctx.new_str(module)));
+			}
+
+			return module_proxy(&import.call(args, vm)?, vm);
+		}
+
+		import.call(args, vm)
+	});
+	vm.builtins.set_attr("__import__", guarded_import, vm)?;
+
+	for name in BLOCKED_BUILTINS {
+		// exit and quit only exist when site has been loaded
+		let Ok(original) = vm.builtins.get_attr(name, vm) else {
+			continue;
+		};
+
+		let project_modules = project_modules.to_vec();
+		let blocked = vm.new_function(name, move |args: FuncArgs, vm: &VirtualMachine| -> PyResult {
+			if called_from_script(vm, &project_modules) {
+				return Err(vm.new_import_error(
+					format!("{name}() is not available in sandbox mode"),
+					vm.ctx.new_str(name),
+				));
+			}
+
+			original.call(args, vm)
+		});
+		vm.builtins.set_attr(name, blocked, vm)?;
+	}
+
+	for name in ATTRIBUTE_BUILTINS {
+		let original = vm.builtins.get_attr(name, vm)?;
+
+		let project_modules = project_modules.to_vec();
+		let guarded = vm.new_function(name, move |args: FuncArgs, vm: &VirtualMachine| -> PyResult {
+			let attribute = args.args.get(1).and_then(|attribute| attribute.payload::<PyStr>());
+			if let Some(attribute) = attribute.filter(|attribute| is_blocked_attribute(attribute.as_str())) {
+				if called_from_script(vm, &project_modules) {
+					return Err(vm.new_attribute_error(blocked_attribute_message(attribute.as_str())));
+				}
+			}
+
+			original.call(args, vm)
+		});
+		vm.builtins.set_attr(name, guarded, vm)?;
+	}
+
+	Ok(())
+}
+
+// A module with only the public attributes of `module`. Submodules are proxied the same way,
+// any other module it imported is left out
+fn module_proxy(module: &PyObjectRef, vm: &VirtualMachine) -> PyResult {
+	let name = module.get_attr("__name__", vm)?.str(vm)?;
+	let Ok(attributes) = module.get_attr("__dict__", vm)?.downcast::<PyDict>() else {
+		return Ok(module.clone());
+	};
+
+	let public = vm.ctx.new_dict();
+	for (key, value) in attributes {
+		let Some(key) = key.payload::<PyStr>().map(|key| key.as_str().to_owned()) else {
+			continue;
+		};
+		if key.starts_with('_') {
+			continue;
+		}
+
+		let value = if value.payload_is::<PyModule>() {
+			let is_submodule = value.get_attr("__name__", vm)
+				.and_then(|submodule| submodule.str(vm))
+				.is_ok_and(|submodule| submodule.as_str() == format!("{}.{key}", name.as_str()));
+			if !is_submodule {
+				continue;
+			}
+
+			module_proxy(&value, vm)?
+		} else {
+			value
+		};
+
+		public.set_item(key.as_str(), value, vm)?;
+	}
+
+	Ok(vm.new_module(name.as_str(), public, None).into())
+}
+
+/// Replaces the modules left in the script's globals, like the prelude's `math`, with proxies.
+/// `__builtins__` stays, its guarded functions are what the script is meant to call.
+pub fn proxy_globals(globals: &PyDictRef, vm: &VirtualMachine) -> PyResult<()> {
+	for (name, value) in globals.clone() {
+		if value.payload_is::<PyModule>() && !name.payload::<PyStr>().is_some_and(|name| name.as_str() == "__builtins__") {
+			globals.set_item(&*name, module_proxy(&value, vm)?, vm)?;
+		}
+	}
+
+	Ok(())
+}
+
+// native functions don't get a frame, so the current frame is the caller's. Frames are told
+// apart by the file their code was compiled from, which the script can't change, rather than
+// by their `__name__` global, which it can
+fn called_from_script(vm: &VirtualMachine, project_modules: &[String]) -> bool {
+	let Some(frame) = vm.current_frame() else {
+		return false;
+	};
+
+	let file = frame.code.source_path.as_str();
+	file == SCRIPT_FILE_NAME || file.strip_suffix(".py").is_some_and(|module| is_project_module(module, project_modules))
+}
@@ -0,0 +1,46 @@
+use std::f32::consts::{PI, TAU};
+
+use bevy::math::Vec2;
+
+/// Where and when a projectile fired now at `speed` meets a target moving in a straight line.
+/// Positions are in world units and velocities in world units per second.
+/// Returns the meeting point and the time to get there, or None when the target is outrunning
+/// the projectile.
+pub fn solve_intercept(shooter: Vec2, target: Vec2, target_velocity: Vec2, speed: f32) -> Option<(Vec2, f32)> {
+	let offset = target - shooter;
+
+	// |offset + target_velocity * t| = speed * t, as a*t^2 + b*t + c = 0
+	let a = target_velocity.length_squared() - speed * speed;
+	let b = 2. * offset.dot(target_velocity);
+	let c = offset.length_squared();
+
+	let time = if a.abs() < f32::EPSILON {
+		// the target is as fast as the projectile, which only catches it head on
+		if b >= 0. {
+			return None;
+		}
+		-c / b
+	} else {
+		let discriminant = b * b - 4. * a * c;
+		if discriminant < 0. {
+			return None;
+		}
+
+		let root = discriminant.sqrt();
+		let (t1, t2) = ((-b - root) / (2. * a), (-b + root) / (2. * a));
+
+		// the soonest time that hasn't already passed
+		match (t1.min(t2), t1.max(t2)) {
+			(earliest, _) if earliest >= 0. => earliest,
+			(_, latest) if latest >= 0. => latest,
+			_ => return None,
+		}
+	};
+
+	Some((target + target_velocity * time, time))
+}
+
+// the same angle in [-PI, PI]
+pub fn wrap_angle(angle: f32) -> f32 {
+	(angle + PI).rem_euclid(TAU) - PI
+}
@@ -0,0 +1,165 @@
+use std::{
+	sync::{atomic::{AtomicBool, Ordering}, Arc, Condvar, Mutex, Weak},
+	thread,
+	time::{Duration, Instant},
+};
+
+use rustpython_vm as vm;
+use vm::{builtins::PyTypeRef, common::static_cell, signal::{user_signal_channel, UserSignalReceiver, UserSignalSender}, VirtualMachine};
+
+pub const BUDGET_EXCEEDED_MESSAGE: &str = "script exceeded budget";
+
+#[derive(Default)]
+struct Deadline {
+	at: Option<Instant>, // None while nothing is running
+	shutdown: bool, // the watchdog was dropped
+}
+
+#[derive(Default)]
+struct WatchdogState {
+	deadline: Mutex<Deadline>,
+	changed: Condvar, // notified when a run starts or ends, and on shutdown
+	tripped: AtomicBool, // the budget exception has been raised during the current run
+}
+
+impl WatchdogState {
+	fn is_overdue(&self) -> bool {
+		matches!(self.deadline.lock().unwrap().at, Some(deadline) if Instant::now() > deadline)
+	}
+
+	fn update(&self, f: impl FnOnce(&mut Deadline)) {
+		f(&mut self.deadline.lock().unwrap());
+		self.changed.notify_one();
+	}
+}
+
+/// `codepilot.BudgetExceeded`, raised into a script that runs past its budget.
+/// It derives from `BaseException` so `except Exception:` doesn't catch it.
+pub fn budget_exceeded_type(vm: &VirtualMachine) -> &'static PyTypeRef {
+	static_cell! {
+		static BUDGET_EXCEEDED: PyTypeRef;
+	}
+
+	BUDGET_EXCEEDED.get_or_init(|| {
+		vm.ctx.new_exception_type("codepilot", "BudgetExceeded", Some(vec![vm.ctx.exceptions.base_exception_type.to_owned()]))
+	})
+}
+
+/// Aborts python code that runs past its budget.
+/// A background thread sleeps until the deadline and, once it passes, raises `BudgetExceeded`
+/// in the interpreter through its user signal channel. The vm checks for signals between
+/// instructions, so this also breaks out of `while True:` loops.
+pub struct Watchdog {
+	state: Arc<WatchdogState>,
+}
+
+impl Watchdog {
+	/// Returns the watchdog and the receiver to install on the interpreter it guards.
+	/// The watch thread exits once the watchdog is dropped.
+	pub fn new() -> (Self, UserSignalReceiver) {
+		let (signals, receiver) = user_signal_channel();
+		let state = Arc::new(WatchdogState::default());
+
+		let watched = state.clone();
+		thread::spawn(move || watch(&watched, &signals));
+
+		(Self { state }, receiver)
+	}
+
+	/// Runs `f` with the budget armed. Returns whether the budget was exceeded.
+	pub fn run<R>(&self, budget: Duration, f: impl FnOnce() -> R) -> (R, bool) {
+		self.state.tripped.store(false, Ordering::Release);
+		self.state.update(|deadline| deadline.at = Some(Instant::now() + budget));
+
+		let result = f();
+
+		self.state.update(|deadline| deadline.at = None);
+		let tripped = self.state.tripped.swap(false, Ordering::AcqRel);
+
+		(result, tripped)
+	}
+
+	/// A handle that stops the clock of the current run, for work done on the script's behalf
+	pub fn pauser(&self) -> WatchdogPauser {
+		WatchdogPauser { state: self.state.clone() }
+	}
+}
+
+impl Drop for Watchdog {
+	fn drop(&mut self) {
+		self.state.update(|deadline| deadline.shutdown = true);
+	}
+}
+
+#[derive(Clone)]
+pub struct WatchdogPauser {
+	state: Arc<WatchdogState>,
+}
+
+impl WatchdogPauser {
+	/// Runs `f` without it counting toward the budget of the current run, if there is one
+	pub fn paused<R>(&self, f: impl FnOnce() -> R) -> R {
+		// disarmed while `f` runs, then armed again with what was left
+		let mut remaining = None;
+		self.state.update(|deadline| {
+			remaining = deadline.at.take().map(|at| at.saturating_duration_since(Instant::now()));
+		});
+
+		let result = f();
+
+		if let Some(remaining) = remaining {
+			self.state.update(|deadline| deadline.at = Some(Instant::now() + remaining));
+		}
+
+		result
+	}
+}
+
+fn watch(state: &Arc<WatchdogState>, signals: &UserSignalSender) {
+	let mut deadline = state.deadline.lock().unwrap();
+	let mut raised_for = None; // the deadline the exception was already raised for
+
+	loop {
+		if deadline.shutdown {
+			return;
+		}
+
+		let Some(at) = deadline.at.filter(|&at| raised_for != Some(at)) else {
+			// idle, or this run has been dealt with, until the next run starts
+			deadline = state.changed.wait(deadline).unwrap();
+			continue;
+		};
+
+		let now = Instant::now();
+		if now <= at {
+			deadline = state.changed.wait_timeout(deadline, at - now).unwrap().0;
+			continue;
+		}
+
+		raised_for = Some(at);
+		if raise_budget_exceeded(Arc::downgrade(state), signals.clone()).is_err() {
+			// the interpreter is gone
+			return;
+		}
+	}
+}
+
+// Queues the exception. Handling it queues it again, so it's raised on every instruction until
+// the run returns: a bare `except:` or `finally:` can catch it, but can't run any code of its
+// own afterwards. The deadline is checked when the signal is handled in case the run finished
+// in the meantime.
+fn raise_budget_exceeded(watched: Weak<WatchdogState>, signals: UserSignalSender) -> Result<(), ()> {
+	let resend = signals.clone();
+	signals.send(Box::new(move |vm| {
+		match watched.upgrade() {
+			Some(state) if state.is_overdue() => {
+				state.tripped.store(true, Ordering::Release);
+				let _ = raise_budget_exceeded(watched, resend);
+
+				Err(vm.new_exception_msg(budget_exceeded_type(vm).clone(), BUDGET_EXCEEDED_MESSAGE.to_owned()))
+			}
+			_ => Ok(()),
+		}
+	}))
+	.map_err(|_| ())
+}
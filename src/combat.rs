@@ -1,6 +1,6 @@
 use bevy::{prelude::*, utils::HashSet, sprite::{collide_aabb::collide, MaterialMesh2dBundle, Mesh2dHandle}, render::mesh};
 
-use crate::{PlayerState, WinSize, EnemyCount, components::{SpriteSize, Laser, FromPlayer, Enemy, FromEnemy, Player, ExplosionToSpawn, Explosion, ExplosionTimer, Weapon, Ship, Shield, EMPAnimator, EMP, Allegiance, WeaponType}, GameTextures, EXPLOSION_LEN, CollidedEntities, events::FireWeaponEvent};
+use crate::{PlayerState, WinSize, EnemyCount, components::{SpriteSize, Laser, FromPlayer, Enemy, FromEnemy, Player, ExplosionToSpawn, Explosion, ExplosionTimer, Weapon, Ship, Shield, EMPAnimator, EMP, Allegiance, WeaponType}, GameTextures, EXPLOSION_LEN, CollidedEntities, events::{FireWeaponEvent, PlayerDestroyedEvent, ShipHitEvent}};
 use bevy::prelude::Entity;
 
 pub struct CombatPlugin;
@@ -9,6 +9,8 @@ impl Plugin for CombatPlugin {
     fn build(&self, app: &mut App) {
         app
         .add_event::<FireWeaponEvent>()
+        .add_event::<ShipHitEvent>()
+        .add_event::<PlayerDestroyedEvent>()
         .add_systems(Update, weapon_cooldown_system)
         .add_systems(Update, laser_hit_system)
         .add_systems(Update, explosion_to_spawn_system)
@@ -49,8 +51,9 @@ fn try_fire_emp_listener(
     mut ev_weapon_fired: EventReader<FireWeaponEvent>,
     mut commands: Commands,
     game_textures: Res<GameTextures>,
+    mut ship_hit_event: EventWriter<ShipHitEvent>,
     mut weapon_query: Query<(&Parent, &mut Weapon)>,
-    mut ship_query: Query<(&mut Ship, &Allegiance, &Transform)>
+    mut ship_query: Query<(Entity, &mut Ship, &Allegiance, &Transform)>
 ) {
 
     for fire_event in ev_weapon_fired.read() {
@@ -74,7 +77,7 @@ fn try_fire_emp_listener(
             let mut firing_ship_allegiance: Option<Allegiance> = None;
 
 
-            if let Ok((firing_ship, fsa, firing_ship_tf)) = ship_query.get_mut(fire_event.firing_entity) {
+            if let Ok((_, firing_ship, fsa, firing_ship_tf)) = ship_query.get_mut(fire_event.firing_entity) {
                 fired_weapon.current_charge = 0.;
                 firing_xy = Some((firing_ship_tf.translation.x, firing_ship_tf.translation.y));
                 firing_ship_allegiance = Some(fsa.clone());
@@ -105,7 +108,7 @@ fn try_fire_emp_listener(
                 }).insert(EMPAnimator::new(1.1));
 
                 // deal damage to enemy ships inversely proportional to distance
-                for (mut ship, ship_allegiance, ship_tf) in ship_query.iter_mut() {
+                for (ship_entity, mut ship, ship_allegiance, ship_tf) in ship_query.iter_mut() {
                     if *ship_allegiance == fsa {
                         continue;
                     }
@@ -119,6 +122,11 @@ fn try_fire_emp_listener(
 
                     ship.current_shields -= damage;
 
+                    ship_hit_event.send(ShipHitEvent {
+                        ship: ship_entity,
+                        damage,
+                        weapon_type: WeaponType::EMP
+                    });
                 }
                 
             }
@@ -134,6 +142,7 @@ fn laser_hit_system(
 	mut enemy_count: ResMut<EnemyCount>,
 	mut player_state: ResMut<PlayerState>,
     mut occured_collisions: ResMut<CollidedEntities>,
+    mut ship_hit_event: EventWriter<ShipHitEvent>,
 	laser_query: Query<(Entity, &Allegiance, &Transform, &SpriteSize), (With<Laser>)>,
 	mut ship_query: Query<(Entity, &mut Ship, &Allegiance, &Transform, &SpriteSize)>
 ) {
@@ -183,7 +192,14 @@ fn laser_hit_system(
                 occured_collisions.0.insert((laser_entity, ship_entity));
 
                 // add damage
-				ship.current_shields -= 0.81;
+				let damage = 0.81;
+				ship.current_shields -= damage;
+
+                ship_hit_event.send(ShipHitEvent {
+                    ship: ship_entity,
+                    damage,
+                    weapon_type: WeaponType::Laser
+                });
 
 				break;
 			}
@@ -261,6 +277,7 @@ fn ship_destroyed_system(
     mut commands: Commands,
     mut player_state: ResMut<PlayerState>,
     mut enemy_count: ResMut<EnemyCount>,
+    mut player_destroyed_event: EventWriter<PlayerDestroyedEvent>,
     time: Res<Time>,
     mut ship_query: Query<(Entity, &mut Ship, &Allegiance, &Transform, Option<&Player>)>,
 ) {
@@ -289,6 +306,7 @@ fn ship_destroyed_system(
             if let Some(player) = player {
                 player_state.shot(time.elapsed_seconds_f64());
 				player_state.score = 0;
+                player_destroyed_event.send(PlayerDestroyedEvent);
                 
             } else {
                 enemy_count.0 -= 1;
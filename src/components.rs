@@ -67,6 +67,15 @@ impl Default for Allegiance {
     }
 }
 
+impl Allegiance {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Allegiance::Friendly => "friendly",
+            Allegiance::Enemy => "enemy",
+        }
+    }
+}
+
 // region:    --- Player Components
 #[derive(Component)]
 pub struct Player;
@@ -102,6 +111,23 @@ impl Default for WeaponType {
     }
 }
 
+impl WeaponType {
+    pub fn name(&self) -> &'static str {
+        match self {
+            WeaponType::Laser => "laser",
+            WeaponType::EMP => "emp",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "laser" => Some(WeaponType::Laser),
+            "emp" => Some(WeaponType::EMP),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Component)]
 pub struct Laser;
 
@@ -167,3 +193,7 @@ pub struct CodePilotActiveText;
 #[derive(Component)]
 pub struct MaxScoreText;
 
+// text drawn by the codepilot script with draw_text, replaced every tick
+#[derive(Component)]
+pub struct ScriptDrawingText;
+
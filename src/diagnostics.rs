@@ -0,0 +1,123 @@
+use std::ops::Range;
+
+use rustpython_parser::{self as parser};
+use parser::{parse, Mode};
+
+// the filename the main file is compiled under, project modules are compiled as <name>.py
+const MAIN_FILE_NAME: &str = "<embedded>";
+
+/// A problem in one of the project files, shown in the editor
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    pub file: String, // module name
+    pub line: usize, // 1 based
+    pub column: Option<usize>, // 0 based, counted from the first non blank character of the line
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub fn line_range(&self, source: &str) -> Option<Range<usize>> {
+        line_range(source, self.line)
+    }
+
+    // byte range of the character the diagnostic points at, if it has a column
+    pub fn column_range(&self, source: &str) -> Option<Range<usize>> {
+        let line = self.line_range(source)?;
+        let text = &source[line.clone()];
+        let indent = text.len() - text.trim_start().len();
+
+        let (offset, c) = text[indent..].char_indices().nth(self.column?)?;
+        let start = line.start + indent + offset;
+
+        Some(start..start + c.len_utf8())
+    }
+}
+
+// byte range of a 1 based line in the source, without the newline
+pub fn line_range(source: &str, line_number: usize) -> Option<Range<usize>> {
+    let mut start = 0;
+
+    for (index, line) in source.split('\n').enumerate() {
+        if index + 1 == line_number {
+            return Some(start..start + line.len());
+        }
+        start += line.len() + 1;
+    }
+
+    None
+}
+
+/// Parses the source as it's being edited, returning the first syntax error
+pub fn parse_error(source: &str, file: &str) -> Option<Diagnostic> {
+    let err = parse(source, Mode::Module, MAIN_FILE_NAME).err()?;
+
+    let offset = (err.offset.to_usize()).min(source.len());
+    let line_start = source[..offset].rfind('\n').map_or(0, |newline| newline + 1);
+    let line_text = source[line_start..].split('\n').next().unwrap_or_default();
+    let indent = line_text.len() - line_text.trim_start().len();
+
+    Some(Diagnostic {
+        file: file.to_owned(),
+        line: source[..offset].matches('\n').count() + 1,
+        column: Some(source[line_start..offset].chars().count().saturating_sub(indent)),
+        message: err.error.to_string(),
+    })
+}
+
+/// Finds where a traceback or syntax error message points into the project, e.g.
+/// ```text
+///   File "<embedded>", line 3, in on_tick
+/// NameError: name 'foo' is not defined
+/// ```
+/// The innermost frame in a project file is used, syntax errors also give the column
+/// from the caret under the quoted line. Frames of other `.py` files, like the stdlib, are
+/// skipped.
+pub fn from_traceback(traceback: &str, main_file: &str, project_modules: &[String]) -> Option<Diagnostic> {
+    let lines = traceback.lines().collect::<Vec<_>>();
+    let mut location = None;
+
+    for (index, line) in lines.iter().enumerate() {
+        let Some(rest) = line.trim_start().strip_prefix("File \"") else {
+            continue;
+        };
+        let Some((file_name, rest)) = rest.split_once('"') else {
+            continue;
+        };
+
+        let file = if file_name == MAIN_FILE_NAME {
+            main_file
+        } else if let Some(module) = file_name
+            .strip_suffix(".py")
+            .filter(|&module| module != main_file && project_modules.iter().any(|project_module| project_module == module))
+        {
+            module
+        } else {
+            continue;
+        };
+
+        let line_number = rest
+            .strip_prefix(", line ")
+            .and_then(|rest| rest.split(|c: char| !c.is_ascii_digit()).next())
+            .and_then(|number| number.parse::<usize>().ok());
+
+        let Some(line_number) = line_number else {
+            continue;
+        };
+
+        // the quoted line is printed without its indentation, followed by the caret
+        let column = match (lines.get(index + 1), lines.get(index + 2)) {
+            (Some(code), Some(caret)) if caret.trim() == "^" => {
+                let code_indent = code.len() - code.trim_start().len();
+                caret.find('^').map(|caret| caret.saturating_sub(code_indent))
+            }
+            _ => None,
+        };
+
+        location = Some((file.to_owned(), line_number, column));
+    }
+
+    let (file, line, column) = location?;
+    let message = lines.iter().rev().find(|line| !line.trim().is_empty())?.trim().to_owned();
+
+    Some(Diagnostic { file, line, column, message })
+}
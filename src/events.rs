@@ -12,3 +12,40 @@ pub struct FireWeaponEvent {
 #[derive(Event)]
 pub struct CompileCodeEvent;
 
+// sent once the project compiled and its session replaced the previous one
+#[derive(Event)]
+pub struct ScriptCompiledEvent;
+
+#[derive(Event)]
+pub struct ClearMemoryEvent;
+
+// from the debugger controls while paused on a breakpoint
+#[derive(Event)]
+pub enum DebuggerActionEvent {
+    Step,
+    Continue,
+    Abort,
+}
+
+#[derive(Event)]
+pub struct SaveScriptEvent {
+    pub slot: String,
+}
+
+#[derive(Event)]
+pub struct LoadScriptEvent {
+    pub slot: String,
+}
+
+#[derive(Event)]
+pub struct PlayerSpawnedEvent;
+
+#[derive(Event)]
+pub struct PlayerDestroyedEvent;
+
+#[derive(Event)]
+pub struct ShipHitEvent {
+    pub ship: Entity,
+    pub damage: f32,
+    pub weapon_type: WeaponType
+}
@@ -11,14 +11,14 @@ use bevy_egui::egui::Id;
 use bevy_egui::egui::text_edit::{CursorRange, CCursorRange};
 use components::{
 	CameraMarker, Enemy, Explosion, ExplosionTimer, ExplosionToSpawn, FromEnemy, FromPlayer, Laser, Movable,
-	Player, SpriteSize, Velocity, ScoreText, MaxScoreText, CodePilotActiveText, WeaponChargeBar
+	Player, SpriteSize, Velocity, ScoreText, MaxScoreText, CodePilotActiveText, WeaponChargeBar, WeaponType
 };
 use bevy::diagnostic::FrameTimeDiagnosticsPlugin;
 use bevy_egui::{egui, EguiContexts, EguiPlugin};
 use events::CompileCodeEvent;
 use rustpython_vm as vm;
 use vm::{builtins::PyCode, PyRef};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, VecDeque};
 use std::sync::atomic::{AtomicBool, Ordering};
 use rand::{Rng, rngs::StdRng, SeedableRng, thread_rng};
 
@@ -26,14 +26,18 @@ use ui::UIPlugin;
 use movement::MovementPlugin;
 use enemy::EnemyPlugin;
 use player::PlayerPlugin;
-use codepilot::CodePilotPlugin;
+use codepilot::{CodePilotPlugin, SandboxPolicy};
+use diagnostics::Diagnostic;
 use combat::CombatPlugin;
+use storage::StoragePlugin;
+use watch::ScriptWatchPlugin;
 use post_processing::{PostProcessPlugin, PostProcessSettings};
 use std::{collections::HashSet, f32::consts::PI};
 
 mod components;
 mod events;
 mod autocomplete;
+mod diagnostics;
 mod ui;
 mod movement;
 mod post_processing;
@@ -41,6 +45,8 @@ mod enemy;
 mod player;
 mod codepilot;
 mod combat;
+mod storage;
+mod watch;
 
 // region:    --- Asset Constants
 
@@ -54,6 +60,7 @@ const PLAYER_SPRITE: &str = "lighter_nose.png";
 const PLAYER_SIZE: (f32, f32) = (144., 75.);
 const PLAYER_LASER_SPRITE: &str = "laser_a_01.png";
 const PLAYER_LASER_SIZE: (f32, f32) = (9., 54.);
+const PLAYER_LASER_SPEED: f32 = 10.; // in velocity units, like `Velocity`
 
 const ENEMY_SPRITE: &str = "organic_enemy.png";
 const ENEMY_SIZE: (f32, f32) = (144., 75.);
@@ -76,6 +83,25 @@ const BASE_SPEED: f32 = 200.;
 const BASE_ROT_SPEED: f32 = 10.;
 
 
+// frame rate the per frame ship accelerations were tuned at
+const REFERENCE_FRAME_RATE: f32 = 60.;
+
+const CODEPILOT_TICK_RATES: [f64; 3] = [10., 20., 60.]; // Hz
+const CODEPILOT_DEFAULT_TICK_RATE: f64 = 20.;
+const CODEPILOT_MAX_FAILED_TICKS: u32 = 10; // consecutive ticks raising an error before codepilot is faulted
+const CODEPILOT_RESUME_KEY: KeyCode = KeyCode::F8; // resumes a faulted codepilot
+const CODEPILOT_MAIN_FILE: &str = "main"; // the project file that runs every tick, the others are imported
+const CODEPILOT_CONSOLE_LINES_PER_TICK: usize = 20; // further printed lines are dropped
+const CODEPILOT_SENSOR_RANGE: f32 = 1200.; // projectiles further away aren't reported to scripts
+const CODEPILOT_HISTORY_LEN: usize = 5000; // history entries kept, the oldest are dropped
+// names a project file can't take, python keywords and the modules provided by the game
+const CODEPILOT_RESERVED_MODULE_NAMES: [&str; 37] = [
+	"False", "None", "True", "and", "as", "assert", "async", "await", "break", "class", "continue", "def",
+	"del", "elif", "else", "except", "finally", "for", "from", "global", "if", "import", "in", "is",
+	"lambda", "nonlocal", "not", "or", "pass", "raise", "return", "try", "while", "with", "yield",
+	"codepilot", "builtins",
+];
+
 const PLAYER_RESPAWN_DELAY: f64 = 2.;
 const ENEMY_MAX: u32 = 3;
 const FORMATION_MEMBERS_MAX: u32 = 3;
@@ -105,20 +131,18 @@ struct GameTextures {
 #[derive(Clone, Debug, PartialEq)]
 pub struct CommandState {
 	fire: bool,
-	forward: bool,
-	backward: bool,
-	clockwise: bool,
-	counter_clockwise: bool,
+	fire_weapons: Vec<WeaponType>, // equipped weapons other than the laser, fired through FireWeaponEvent
+	throttle: f32, // -1 (full reverse) to 1 (full forward)
+	turn: f32, // -1 (full clockwise) to 1 (full counter clockwise)
 }
 
 impl CommandState {
 	pub fn default() -> Self {
 		Self {
 			fire: false,
-			forward: false,
-			backward: false,
-			clockwise: false,
-			counter_clockwise: false
+			fire_weapons: Vec::new(),
+			throttle: 0.,
+			turn: 0.,
 		}
 	}
 }
@@ -139,23 +163,141 @@ pub enum PyDebugMessage {
     KeyedDebug(KeyedDebug),
 }
 
+// a global or pinned expression in the watch panel
+#[derive(Clone, PartialEq)]
+pub struct WatchValue {
+	name: String, // the global's name, or the expression
+	type_name: String,
+	value: String, // repr, or the error for an expression that failed
+	has_changed: bool, // since the previous tick
+}
+
+#[derive(Clone, PartialEq)]
+pub struct ConsoleLine {
+	text: String,
+	is_error: bool, // written to stderr
+	repeats: u32, // identical lines printed one after another are collapsed into one, even with other output in between
+}
+
 pub enum CodePilotOutput {
 	DebugMessages(Vec<PyDebugMessage>),
-	CommandState(CommandState)
+	CommandState(CommandState),
+	Console(ConsoleLine),
 }
 
-pub type CodePilotHist = Vec<(f32, CodePilotOutput)>;
+/// The codepilot output over time, oldest first.
+/// Bounded to `CODEPILOT_HISTORY_LEN` entries so a long session doesn't keep growing it
+pub struct CodePilotHist {
+	entries: VecDeque<(f32, CodePilotOutput)>,
+}
+
+impl Default for CodePilotHist {
+	fn default() -> Self {
+		Self {
+			entries: VecDeque::with_capacity(CODEPILOT_HISTORY_LEN),
+		}
+	}
+}
+
+impl CodePilotHist {
+	pub fn push(&mut self, entry: (f32, CodePilotOutput)) {
+		if self.entries.len() >= CODEPILOT_HISTORY_LEN {
+			self.entries.pop_front();
+		}
+		self.entries.push_back(entry);
+	}
+
+	pub fn iter(&self) -> impl DoubleEndedIterator<Item = &(f32, CodePilotOutput)> {
+		self.entries.iter()
+	}
+
+	pub fn get(&self, index: usize) -> Option<&(f32, CodePilotOutput)> {
+		self.entries.get(index)
+	}
+
+	// counts another print of the newest console line when the text matches, moving it to the
+	// end so the entries stay in time order. Returns false when it's a different line
+	pub fn repeat_console_line(&mut self, time: f32, text: &str, is_error: bool) -> bool {
+		let last_console = self.entries.iter().rposition(|(_, output)| matches!(output, CodePilotOutput::Console(_)));
+		let Some(index) = last_console else {
+			return false;
+		};
+
+		let is_repeat = matches!(
+			&self.entries[index],
+			(_, CodePilotOutput::Console(line)) if line.text == text && line.is_error == is_error
+		);
+		if !is_repeat {
+			return false;
+		}
+
+		let Some((_, CodePilotOutput::Console(mut line))) = self.entries.remove(index) else {
+			return false;
+		};
+		line.repeats += 1;
+		self.entries.push_back((time, CodePilotOutput::Console(line)));
+		true
+	}
+
+	pub fn len(&self) -> usize {
+		self.entries.len()
+	}
+
+	// times of the oldest and newest entries
+	pub fn time_range(&self) -> Option<(f32, f32)> {
+		Some((self.entries.front()?.0, self.entries.back()?.0))
+	}
+
+	// index of the last entry at or before the time
+	pub fn index_at(&self, time: f32) -> Option<usize> {
+		self.entries.partition_point(|(entry_time, _)| *entry_time <= time).checked_sub(1)
+	}
+
+	// the latest command state and debug messages at or before the time
+	pub fn state_at(&self, time: f32) -> (Option<&CommandState>, Option<&Vec<PyDebugMessage>>) {
+		let mut command_state = None;
+		let mut debug_messages = None;
+
+		for (_, output) in self.entries.iter().rev().skip_while(|(entry_time, _)| *entry_time > time) {
+			match output {
+				CodePilotOutput::CommandState(state) if command_state.is_none() => command_state = Some(state),
+				CodePilotOutput::DebugMessages(messages) if debug_messages.is_none() => debug_messages = Some(messages),
+				_ => {}
+			}
+
+			if command_state.is_some() && debug_messages.is_some() {
+				break;
+			}
+		}
+
+		(command_state, debug_messages)
+	}
+}
 
 #[derive(Resource)]
 pub struct CodePilotCode {
 	raw_code: String,
     compiled: Option<PyRef<PyCode>>,
-	py_result: Option<String>,
-	codepilot_hist: Vec<(f32, CodePilotOutput)>, // time, command state
+	py_result: Option<String>, // the last runtime error, cleared by a tick that runs cleanly
+	compile_result: Option<String>, // compile, project file and storage messages, ticks leave it alone
+	parsed: Option<(String, String, Option<Diagnostic>)>, // file, source and syntax error as of the last parse
+	codepilot_hist: CodePilotHist, // time, command state
+	hist_scrub_time: Option<f32>, // the moment picked on the timeline, None follows the latest tick
+	hist_scrolled_index: Option<usize>, // the history row last scrolled into view for the scrub time
 	completions: Vec<String>,
 	autocomplete_token: String,
 	cursor_range: Option<CCursorRange>,
 	selected_completion: usize,
+	failed_ticks: u32, // consecutive ticks that raised an error
+	faulted: bool, // suspended after too many failed ticks, until resumed
+	memory: Vec<(String, String)>, // repr of the script's memory entries, as of the last tick
+	project_files: BTreeMap<String, String>, // module name -> source, the active file is edited in raw_code
+	active_file: String,
+	new_file_name: String,
+	watch_globals: Vec<WatchValue>, // the script's own globals, as of the last tick
+	watch_expressions: Vec<String>, // pinned in the watch panel, evaluated after every tick
+	watch_pins: Vec<WatchValue>, // results of the pinned expressions
+	new_watch_expression: String,
 }
 impl Default for CodePilotCode {
 	fn default() -> Self {
@@ -163,11 +305,136 @@ impl Default for CodePilotCode {
 			raw_code: String::new(),
 			compiled: None,
 			py_result: None,
-			codepilot_hist: Vec::new(),
+			compile_result: None,
+			parsed: None,
+			codepilot_hist: CodePilotHist::default(),
+			hist_scrub_time: None,
+			hist_scrolled_index: None,
 			completions: Vec::new(),
 			autocomplete_token: String::new(),
 			cursor_range: None,
-			selected_completion: 0
+			selected_completion: 0,
+			failed_ticks: 0,
+			faulted: false,
+			memory: Vec::new(),
+			project_files: BTreeMap::from([(CODEPILOT_MAIN_FILE.to_owned(), String::new())]),
+			active_file: CODEPILOT_MAIN_FILE.to_owned(),
+			new_file_name: String::new(),
+			watch_globals: Vec::new(),
+			watch_expressions: Vec::new(),
+			watch_pins: Vec::new(),
+			new_watch_expression: String::new(),
+		}
+	}
+}
+
+impl CodePilotCode {
+	// every file in the project, with the editor contents for the active one
+	pub fn project_sources(&self) -> BTreeMap<String, String> {
+		let mut files = self.project_files.clone();
+		files.insert(self.active_file.clone(), self.raw_code.clone());
+		files
+	}
+
+	// the syntax error in the open file, only parsed again once it has been edited
+	pub fn parse_error(&mut self) -> Option<Diagnostic> {
+		let is_stale = self.parsed.as_ref().map_or(true, |(file, source, _)| *file != self.active_file || *source != self.raw_code);
+		if is_stale {
+			let diagnostic = diagnostics::parse_error(&self.raw_code, &self.active_file);
+			self.parsed = Some((self.active_file.clone(), self.raw_code.clone(), diagnostic));
+		}
+
+		self.parsed.as_ref().and_then(|(_, _, diagnostic)| diagnostic.clone())
+	}
+
+	// replaces the whole project, e.g. with one loaded from disk, and opens the main file
+	pub fn load_project(&mut self, mut files: BTreeMap<String, String>) {
+		self.raw_code = files.remove(CODEPILOT_MAIN_FILE).unwrap_or_default();
+		files.insert(CODEPILOT_MAIN_FILE.to_owned(), String::new());
+
+		self.project_files = files;
+		self.active_file = CODEPILOT_MAIN_FILE.to_owned();
+		self.completions = Vec::new();
+		self.cursor_range = None;
+	}
+
+	// replaces the contents of a file, adding it if it's new
+	pub fn set_file_source(&mut self, name: &str, source: String) {
+		if name == self.active_file {
+			self.raw_code = source;
+			self.completions = Vec::new();
+			self.cursor_range = None;
+		} else {
+			self.project_files.insert(name.to_owned(), source);
+		}
+	}
+
+	// swaps the editor over to another file of the project
+	pub fn open_file(&mut self, name: &str) {
+		if name == self.active_file || !self.project_files.contains_key(name) {
+			return;
+		}
+
+		let code = std::mem::take(&mut self.raw_code);
+		self.project_files.insert(self.active_file.clone(), code);
+
+		self.raw_code = self.project_files[name].clone();
+		self.active_file = name.to_owned();
+		self.completions = Vec::new();
+		self.cursor_range = None;
+	}
+
+	// adds an empty module and opens it, the name has to be importable
+	pub fn add_file(&mut self, name: &str) -> Result<(), String> {
+		let is_identifier = name.chars().next().is_some_and(|c| c.is_alphabetic() || c == '_')
+			&& name.chars().all(|c| c.is_alphanumeric() || c == '_');
+
+		if !is_identifier {
+			return Err(format!("'{name}' is not a valid module name"));
+		}
+
+		// standard library names are checked on compile, when there's an interpreter to ask
+		if CODEPILOT_RESERVED_MODULE_NAMES.contains(&name) || name.starts_with("__") {
+			return Err(format!("'{name}' is a reserved name"));
+		}
+
+		if self.project_files.contains_key(name) {
+			return Err(format!("{name}.py already exists"));
+		}
+
+		self.project_files.insert(name.to_owned(), String::new());
+		self.open_file(name);
+		Ok(())
+	}
+
+	// removes the active file, the main file can't be removed
+	pub fn remove_active_file(&mut self) {
+		if self.active_file == CODEPILOT_MAIN_FILE {
+			return;
+		}
+
+		let removed = std::mem::replace(&mut self.active_file, CODEPILOT_MAIN_FILE.to_owned());
+		self.project_files.remove(&removed);
+		self.raw_code = self.project_files[CODEPILOT_MAIN_FILE].clone();
+		self.completions = Vec::new();
+		self.cursor_range = None;
+	}
+}
+
+#[derive(Resource)]
+pub struct CodePilotSettings {
+	tick_rate: f64, // Hz, the script runs on a fixed timestep independent of the frame rate
+	sandbox: SandboxPolicy,
+	keep_memory_on_recompile: bool,
+	keep_memory_on_respawn: bool,
+}
+impl Default for CodePilotSettings {
+	fn default() -> Self {
+		Self {
+			tick_rate: CODEPILOT_DEFAULT_TICK_RATE,
+			sandbox: SandboxPolicy::default(),
+			keep_memory_on_recompile: false,
+			keep_memory_on_respawn: false,
 		}
 	}
 }
@@ -221,6 +488,7 @@ fn main() {
 		// .insert_resource(ClearColor(Color::rgb(0., 0., 0.)))
 		.insert_resource(ClearColor(Color::rgb(0.00, 0.00, 0.08)))
 		.init_resource::<CodePilotCode>()
+		.init_resource::<CodePilotSettings>()
 		.add_plugins(FrameTimeDiagnosticsPlugin::default())
 		// .add_plugins(LogDiagnosticsPlugin::default())
 		.add_plugins(DefaultPlugins.set(WindowPlugin {
@@ -237,6 +505,8 @@ fn main() {
 		.add_plugins(MovementPlugin)
 		.add_plugins(PlayerPlugin)
 		.add_plugins(CodePilotPlugin)
+		.add_plugins(StoragePlugin)
+		.add_plugins(ScriptWatchPlugin)
 		.add_plugins(EnemyPlugin)
 		.add_plugins(CombatPlugin)
 		.add_systems(Startup, setup_system)
@@ -1,8 +1,8 @@
 use crate::combat::spawn_shield_sprite;
-use crate::components::{FromPlayer, Laser, Movable, Player, SpriteSize, Velocity, ExplosionToSpawn, Enemy, Weapon, Ship, EMP, Allegiance};
-use crate::events::FireWeaponEvent;
+use crate::components::{FromPlayer, Laser, Movable, Player, SpriteSize, Velocity, ExplosionToSpawn, Enemy, Weapon, Ship, EMP, Allegiance, WeaponType};
+use crate::events::{FireWeaponEvent, PlayerSpawnedEvent};
 use crate::{
-	GameTextures, PlayerState, WinSize, PLAYER_LASER_SIZE, PLAYER_RESPAWN_DELAY, PLAYER_SIZE,
+	GameTextures, PlayerState, WinSize, PLAYER_LASER_SIZE, PLAYER_LASER_SPEED, PLAYER_RESPAWN_DELAY, PLAYER_SIZE,
 	SPRITE_SCALE, CodePilotCode, enemy
 };
 use bevy::sprite::MaterialMesh2dBundle;
@@ -17,6 +17,7 @@ pub struct PlayerPlugin;
 impl Plugin for PlayerPlugin {
 	fn build(&self, app: &mut App) {
 		app.insert_resource(PlayerState::default())
+			.add_event::<PlayerSpawnedEvent>()
 			.add_systems(
 				Update,
 				player_spawn_system.run_if(on_timer(Duration::from_secs_f32(0.5))),
@@ -31,6 +32,7 @@ impl Plugin for PlayerPlugin {
 fn player_spawn_system(
 	mut commands: Commands,
 	mut player_state: ResMut<PlayerState>,
+	mut player_spawned_event: EventWriter<PlayerSpawnedEvent>,
 	mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<ColorMaterial>>,
 	time: Res<Time>,
@@ -112,6 +114,7 @@ fn player_spawn_system(
 			
 
 		player_state.spawned();
+		player_spawned_event.send(PlayerSpawnedEvent);
 	}
 }
 
@@ -134,6 +137,7 @@ fn player_upgrade_system(
 				charge_rate: 0.5,
 			})
 			.insert(EMP)
+			.insert(WeaponType::EMP)
 			.id();
 
 			commands.entity(player).push_children(&[child]);
@@ -158,7 +162,7 @@ pub fn try_fire_weapon(
 	let x_offset = PLAYER_SIZE.0 / 2. * SPRITE_SCALE - 5.;
 
 	let mut spawn_laser = |x_offset: f32| {
-		let velocity = player_tf.rotation * Vec3::X * 10.0;
+		let velocity = player_tf.rotation * Vec3::X * PLAYER_LASER_SPEED;
 
 		commands
 			.spawn(SpriteBundle {
@@ -192,18 +196,19 @@ pub fn accelerate_forward(
 	velocity: &mut Velocity,
 	transform: &Transform,
 	acceleration: f32,
+	throttle: f32, // 0 to 1, scales the acceleration and the engine flame
 	max_speed: f32,
 	heading: Vec3,
 	heading_perp: Vec3,
 	commands: &mut Commands,
 ) {
-	velocity.x += heading.x * acceleration;
-	velocity.y += heading.y * acceleration;
+	velocity.x += heading.x * acceleration * throttle;
+	velocity.y += heading.y * acceleration * throttle;
 
 	commands.spawn((ExplosionToSpawn {
 		transform: Transform {
 			translation: transform.translation - Vec3::Z - heading * 25.,
-			scale: Vec3{x: 0.6, y: 0.2, z: 1.},
+			scale: Vec3{x: 0.6 * throttle, y: 0.2, z: 1.},
 			rotation: transform.rotation,
 			..Default::default()
 		},
@@ -215,7 +220,7 @@ pub fn accelerate_forward(
 	commands.spawn((ExplosionToSpawn {
 		transform: Transform {
 			translation: transform.translation - Vec3::Z - heading * 15. + heading_perp * 20.,
-			scale: Vec3{x: 0.3, y: 0.15, z: 1.},
+			scale: Vec3{x: 0.3 * throttle, y: 0.15, z: 1.},
 			rotation: transform.rotation,
 			..Default::default()
 		},
@@ -226,7 +231,7 @@ pub fn accelerate_forward(
 	commands.spawn((ExplosionToSpawn {
 		transform: Transform {
 			translation: transform.translation - Vec3::Z - heading * 15. - heading_perp * 20.,
-			scale: Vec3{x: 0.3, y: 0.15, z: 1.},
+			scale: Vec3{x: 0.3 * throttle, y: 0.15, z: 1.},
 			rotation: transform.rotation,
 			..Default::default()
 		},
@@ -239,19 +244,20 @@ pub fn accelerate_backward(
 	velocity: &mut Velocity,
 	transform: &Transform,
 	acceleration: f32,
+	throttle: f32, // 0 to 1, scales the acceleration and the engine flame
 	max_speed: f32,
 	heading: Vec3,
 	heading_perp: Vec3,
 	commands: &mut Commands,
 ) {
-	velocity.x -= heading.x * acceleration;
-	velocity.y -= heading.y * acceleration;
+	velocity.x -= heading.x * acceleration * throttle;
+	velocity.y -= heading.y * acceleration * throttle;
 
 
 	commands.spawn((ExplosionToSpawn {
 		transform: Transform {
 			translation: transform.translation - Vec3::Z + heading * 15. + heading_perp * 20.,
-			scale: Vec3{x: 0.3, y: 0.15, z: 1.},
+			scale: Vec3{x: 0.3 * throttle, y: 0.15, z: 1.},
 			rotation: transform.rotation,
 			..Default::default()
 		},
@@ -262,7 +268,7 @@ pub fn accelerate_backward(
 	commands.spawn((ExplosionToSpawn {
 		transform: Transform {
 			translation: transform.translation - Vec3::Z + heading * 15. - heading_perp * 20.,
-			scale: Vec3{x: 0.3, y: 0.15, z: 1.},
+			scale: Vec3{x: 0.3 * throttle, y: 0.15, z: 1.},
 			rotation: transform.rotation,
 			..Default::default()
 		},
@@ -275,12 +281,13 @@ pub fn accelerate_counter_clockwise(
 	velocity: &mut Velocity,
 	transform: &Transform,
 	ang_acceleration: f32,
+	throttle: f32, // 0 to 1, scales the acceleration and the engine flame
 	max_ang_velocity: f32,
 	heading: Vec3,
 	heading_perp: Vec3,
 	commands: &mut Commands,
 ) {
-	velocity.omega += ang_acceleration;
+	velocity.omega += ang_acceleration * throttle;
 
 	if velocity.omega > max_ang_velocity {
 		velocity.omega = max_ang_velocity;
@@ -289,7 +296,7 @@ pub fn accelerate_counter_clockwise(
 	commands.spawn((ExplosionToSpawn {
 		transform: Transform {
 			translation: transform.translation - Vec3::Z + heading * 10. - heading_perp * 30.,
-			scale: Vec3{x: 0.3, y: 0.1, z: 1.},
+			scale: Vec3{x: 0.3 * throttle, y: 0.1, z: 1.},
 			rotation: transform.rotation.mul_quat(Quat::from_rotation_z(PI / 2.)),
 			..Default::default()
 		},
@@ -302,12 +309,13 @@ pub fn accelerate_clockwise (
 	velocity: &mut Velocity,
 	transform: &Transform,
 	ang_acceleration: f32,
+	throttle: f32, // 0 to 1, scales the acceleration and the engine flame
 	max_ang_velocity: f32,
 	heading: Vec3,
 	heading_perp: Vec3,
 	commands: &mut Commands,
 ) {
-	velocity.omega -= ang_acceleration;
+	velocity.omega -= ang_acceleration * throttle;
 
 	if velocity.omega < -max_ang_velocity {
 		velocity.omega = -max_ang_velocity;
@@ -316,7 +324,7 @@ pub fn accelerate_clockwise (
 	commands.spawn((ExplosionToSpawn {
 		transform: Transform {
 			translation: transform.translation - Vec3::Z + heading * 10. + heading_perp * 30.,
-			scale: Vec3{x: 0.3, y: 0.1, z: 1.},
+			scale: Vec3{x: 0.3 * throttle, y: 0.1, z: 1.},
 			rotation: transform.rotation.mul_quat(Quat::from_rotation_z(PI / 2.)),
 			..Default::default()
 		},
@@ -385,24 +393,24 @@ fn player_keyboard_event_system(
 		// Player keyboard control section
 		if kb.pressed(KeyCode::W) {
 			accelerate_forward(
-				&mut velocity, transform, acceleration, max_speed, heading, heading_perp, &mut commands
+				&mut velocity, transform, acceleration, 1., max_speed, heading, heading_perp, &mut commands
 			)
 		}
 
 		if kb.pressed(KeyCode::S) {
 			accelerate_backward(
-				&mut velocity, transform, acceleration, max_speed, heading, heading_perp, &mut commands
+				&mut velocity, transform, acceleration, 1., max_speed, heading, heading_perp, &mut commands
 			)
 		}
 
 		if kb.pressed(KeyCode::A) {
 			accelerate_counter_clockwise(
-				&mut velocity, transform, ang_acceleration, max_ang_velocity, heading, heading_perp, &mut commands)
+				&mut velocity, transform, ang_acceleration, 1., max_ang_velocity, heading, heading_perp, &mut commands)
 		}
 
 		if kb.pressed(KeyCode::D) {
 			accelerate_clockwise(
-				&mut velocity, transform, ang_acceleration, max_ang_velocity, heading, heading_perp, &mut commands)
+				&mut velocity, transform, ang_acceleration, 1., max_ang_velocity, heading, heading_perp, &mut commands)
 	
 		}
 		
@@ -0,0 +1,190 @@
+use std::{collections::BTreeMap, fs, io, path::PathBuf};
+
+use bevy::prelude::*;
+
+use crate::{events::{LoadScriptEvent, SaveScriptEvent, ScriptCompiledEvent}, CodePilotCode};
+
+const DEFAULT_SLOT: &str = "default";
+const LAST_SLOT_FILE: &str = "last_slot";
+
+pub struct StoragePlugin;
+
+impl Plugin for StoragePlugin {
+	fn build(&self, app: &mut App) {
+		app
+		.add_event::<SaveScriptEvent>()
+		.add_event::<LoadScriptEvent>()
+		.init_resource::<ScriptSlots>()
+		.add_systems(Startup, restore_last_slot_system)
+		.add_systems(Update, script_autosave_system)
+		.add_systems(Update, save_script_system)
+		.add_systems(Update, load_script_system);
+	}
+}
+
+/// Resource - the named script slots on disk, and the one being edited
+#[derive(Resource)]
+pub struct ScriptSlots {
+	pub current: String,
+	pub available: Vec<String>,
+	pub new_slot_name: String,
+}
+
+impl Default for ScriptSlots {
+	fn default() -> Self {
+		Self {
+			current: DEFAULT_SLOT.to_owned(),
+			available: Vec::new(),
+			new_slot_name: String::new(),
+		}
+	}
+}
+
+// scripts live in the per user data directory, e.g. ~/.local/share/codepilot on linux.
+// each slot is a directory holding the project files as <module>.py
+fn storage_dir() -> io::Result<PathBuf> {
+	dirs_next::data_dir()
+		.map(|dir| dir.join("codepilot"))
+		.ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no data directory for this user"))
+}
+
+fn slot_dir(slot: &str) -> io::Result<PathBuf> {
+	Ok(storage_dir()?.join("slots").join(slot))
+}
+
+fn list_slots() -> io::Result<Vec<String>> {
+	let slots_dir = storage_dir()?.join("slots");
+	if !slots_dir.exists() {
+		return Ok(Vec::new());
+	}
+
+	let mut slots = fs::read_dir(slots_dir)?
+		.filter_map(|entry| entry.ok())
+		.filter(|entry| entry.path().is_dir())
+		.filter_map(|entry| entry.file_name().into_string().ok())
+		.collect::<Vec<_>>();
+	slots.sort();
+
+	Ok(slots)
+}
+
+fn save_slot(slot: &str, files: &BTreeMap<String, String>) -> io::Result<()> {
+	let dir = slot_dir(slot)?;
+	fs::create_dir_all(&dir)?;
+
+	for (name, source) in files {
+		fs::write(dir.join(format!("{name}.py")), source)?;
+	}
+
+	// files deleted from the project are deleted from the slot too
+	for entry in fs::read_dir(&dir)?.filter_map(|entry| entry.ok()) {
+		let path = entry.path();
+		let is_stale = path.extension().is_some_and(|ext| ext == "py")
+			&& path.file_stem().and_then(|stem| stem.to_str()).is_some_and(|stem| !files.contains_key(stem));
+
+		if is_stale {
+			fs::remove_file(path)?;
+		}
+	}
+
+	fs::write(storage_dir()?.join(LAST_SLOT_FILE), slot)
+}
+
+fn load_slot(slot: &str) -> io::Result<BTreeMap<String, String>> {
+	let mut files = BTreeMap::new();
+
+	for entry in fs::read_dir(slot_dir(slot)?)?.filter_map(|entry| entry.ok()) {
+		let path = entry.path();
+		if path.extension().is_some_and(|ext| ext == "py") {
+			if let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) {
+				files.insert(name.to_owned(), fs::read_to_string(&path)?);
+			}
+		}
+	}
+
+	fs::write(storage_dir()?.join(LAST_SLOT_FILE), slot)?;
+
+	Ok(files)
+}
+
+// slot names become directory names
+fn is_valid_slot_name(slot: &str) -> bool {
+	!slot.is_empty() && slot.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-' || c == ' ')
+}
+
+fn restore_last_slot_system(
+	mut codepilot_code: ResMut<CodePilotCode>,
+	mut slots: ResMut<ScriptSlots>,
+) {
+	slots.available = list_slots().unwrap_or_default();
+
+	let last_slot = storage_dir()
+		.and_then(|dir| fs::read_to_string(dir.join(LAST_SLOT_FILE)))
+		.map(|slot| slot.trim().to_owned());
+
+	if let Ok(last_slot) = last_slot {
+		match load_slot(&last_slot) {
+			Ok(files) => {
+				info!("Restored script slot '{last_slot}'");
+				codepilot_code.load_project(files);
+				slots.current = last_slot;
+			}
+			Err(err) => warn!("Could not restore script slot '{last_slot}': {err}"),
+		}
+	}
+}
+
+// every successful compile is written to the current slot
+fn script_autosave_system(
+	mut compiled_events: EventReader<ScriptCompiledEvent>,
+	mut save_events: EventWriter<SaveScriptEvent>,
+	slots: Res<ScriptSlots>,
+) {
+	if compiled_events.read().count() > 0 {
+		save_events.send(SaveScriptEvent { slot: slots.current.clone() });
+	}
+}
+
+fn save_script_system(
+	mut save_events: EventReader<SaveScriptEvent>,
+	mut codepilot_code: ResMut<CodePilotCode>,
+	mut slots: ResMut<ScriptSlots>,
+) {
+	for ev in save_events.read() {
+		if !is_valid_slot_name(&ev.slot) {
+			codepilot_code.compile_result = Some(format!("'{}' is not a valid slot name", ev.slot));
+			continue;
+		}
+
+		match save_slot(&ev.slot, &codepilot_code.project_sources()) {
+			Ok(()) => {
+				slots.current = ev.slot.clone();
+				if !slots.available.contains(&ev.slot) {
+					slots.available.push(ev.slot.clone());
+					slots.available.sort();
+				}
+			}
+			Err(err) => {
+				codepilot_code.compile_result = Some(format!("Could not save script slot '{}': {err}", ev.slot));
+			}
+		}
+	}
+}
+
+fn load_script_system(
+	mut load_events: EventReader<LoadScriptEvent>,
+	mut codepilot_code: ResMut<CodePilotCode>,
+	mut slots: ResMut<ScriptSlots>,
+) {
+	for ev in load_events.read() {
+		match load_slot(&ev.slot) {
+			Ok(files) => {
+				codepilot_code.load_project(files);
+				slots.current = ev.slot.clone();
+			}
+			Err(err) => {
+				codepilot_code.compile_result = Some(format!("Could not load script slot '{}': {err}", ev.slot));
+			}
+		}
+	}
+}
@@ -3,7 +3,15 @@ use bevy_egui::{EguiContexts, egui::{self, Pos2, text_edit::{CCursorRange, Curso
 
 use egui_extras::syntax_highlighting::highlight;
 
-use crate::{autocomplete, components::{CodePilotActiveText, ScoreText, WeaponChargeBar}, events::CompileCodeEvent, CodePilotCode, CodePilotOutput, PlayerState, PyDebugMessage};
+use crate::{autocomplete, diagnostics, components::{CodePilotActiveText, ScoreText, WeaponChargeBar}, codepilot::{line_has_call, DebugPlots, ScriptDebugger, ScriptDrawings}, events::{ClearMemoryEvent, CompileCodeEvent, DebuggerActionEvent, LoadScriptEvent, SaveScriptEvent}, storage::ScriptSlots, watch::WatchedScript, CodePilotCode, CodePilotOutput, CodePilotSettings, PlayerState, PyDebugMessage, WatchValue, CODEPILOT_MAIN_FILE, CODEPILOT_RESUME_KEY, CODEPILOT_TICK_RATES};
+
+const EDITOR_MARGIN: f32 = 4.;
+const EDITOR_GUTTER_WIDTH: f32 = 14.; // room for the breakpoint and error markers left of the code
+const ERROR_LINE_COLOR: egui::Color32 = egui::Color32::from_rgba_premultiplied(70, 10, 10, 70);
+const ERROR_MARKER_COLOR: egui::Color32 = egui::Color32::from_rgb(230, 60, 60);
+const BREAKPOINT_COLOR: egui::Color32 = egui::Color32::from_rgb(170, 30, 30);
+const STOP_LINE_COLOR: egui::Color32 = egui::Color32::from_rgba_premultiplied(70, 70, 10, 70);
+const WATCH_CHANGED_COLOR: egui::Color32 = egui::Color32::from_rgb(240, 200, 80);
 
 pub struct UIPlugin;
 
@@ -103,7 +111,17 @@ fn ui_setup_system (
 
 fn egui_system(
 	mut codepilot_code: ResMut<CodePilotCode>,
+    mut codepilot_settings: ResMut<CodePilotSettings>,
     mut compile_code_event: EventWriter<CompileCodeEvent>,
+    mut script_slots: ResMut<ScriptSlots>,
+    mut save_script_event: EventWriter<SaveScriptEvent>,
+    mut load_script_event: EventWriter<LoadScriptEvent>,
+    mut watched_script: ResMut<WatchedScript>,
+    mut clear_memory_event: EventWriter<ClearMemoryEvent>,
+    mut debugger: ResMut<ScriptDebugger>,
+    mut debugger_action_event: EventWriter<DebuggerActionEvent>,
+    mut script_drawings: ResMut<ScriptDrawings>,
+    mut debug_plots: ResMut<DebugPlots>,
 	mut contexts: EguiContexts,
 ) {
     let ctx = contexts.ctx_mut();
@@ -114,15 +132,296 @@ fn egui_system(
     	.show(ctx, |ui| {
                 
             ui.vertical(|ui| {
+                // only write back on change so the tick rate system doesn't reset the timestep every frame
+                let mut tick_rate = codepilot_settings.tick_rate;
+                ui.horizontal(|ui| {
+                    ui.label("Tick Rate: ");
+                    for rate in CODEPILOT_TICK_RATES {
+                        ui.selectable_value(&mut tick_rate, rate, format!("{rate} Hz"));
+                    }
+                });
+                if tick_rate != codepilot_settings.tick_rate {
+                    codepilot_settings.tick_rate = tick_rate;
+                }
+
+                let mut sandbox = codepilot_settings.sandbox.clone();
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut sandbox.enabled, "Sandbox")
+                        .on_hover_text("Only allow importing the listed modules, and hide their internals and the dunder attributes that lead out of the script. Applies on the next compile");
+                    ui.add_enabled(
+                        sandbox.enabled,
+                        egui::TextEdit::singleline(&mut sandbox.allowed_modules).hint_text("allowed modules"),
+                    );
+                });
+                if sandbox != codepilot_settings.sandbox {
+                    codepilot_settings.sandbox = sandbox;
+                }
+
+                // only write back on change, the text system respawns the drawn text when it changes
+                let mut show_drawings = script_drawings.visible;
+                ui.checkbox(&mut show_drawings, "Show Script Drawings")
+                    .on_hover_text("Shapes drawn with draw_line, draw_circle, draw_arrow and draw_text");
+                if show_drawings != script_drawings.visible {
+                    script_drawings.visible = show_drawings;
+                }
+
+                // the script's `memory` dict, as of the last tick
+                egui::CollapsingHeader::new(format!("Memory ({})", codepilot_code.memory.len()))
+                    .id_source("codepilot_memory")
+                    .show(ui, |ui| {
+                        let mut keep_on_recompile = codepilot_settings.keep_memory_on_recompile;
+                        let mut keep_on_respawn = codepilot_settings.keep_memory_on_respawn;
+                        ui.horizontal(|ui| {
+                            ui.checkbox(&mut keep_on_recompile, "Keep on recompile")
+                                .on_hover_text("Only python literals (numbers, strings, lists, dicts...) are carried over");
+                            ui.checkbox(&mut keep_on_respawn, "Keep on respawn");
+                            if ui.button("Clear").clicked() {
+                                clear_memory_event.send(ClearMemoryEvent);
+                            }
+                        });
+                        if keep_on_recompile != codepilot_settings.keep_memory_on_recompile {
+                            codepilot_settings.keep_memory_on_recompile = keep_on_recompile;
+                        }
+                        if keep_on_respawn != codepilot_settings.keep_memory_on_respawn {
+                            codepilot_settings.keep_memory_on_respawn = keep_on_respawn;
+                        }
+
+                        for (key, value) in codepilot_code.memory.iter() {
+                            ui.monospace(format!("{key}: {value}"));
+                        }
+                    });
+
+                // the script's globals and pinned expressions, as of the last tick
+                egui::CollapsingHeader::new("Watch")
+                    .id_source("codepilot_watch")
+                    .show(ui, |ui| {
+                        ui.horizontal(|ui| {
+                            let input = ui.add(egui::TextEdit::singleline(&mut codepilot_code.new_watch_expression).hint_text("expression").desired_width(200.));
+                            let submitted = input.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+
+                            let expression = codepilot_code.new_watch_expression.trim().to_owned();
+                            if (ui.button("Pin").clicked() || submitted) && !expression.is_empty() {
+                                codepilot_code.watch_expressions.push(expression);
+                                codepilot_code.new_watch_expression.clear();
+                            }
+                        });
+
+                        let mut unpinned = None;
+                        egui::Grid::new("codepilot_watch_pins").striped(true).show(ui, |ui| {
+                            for (index, expression) in codepilot_code.watch_expressions.iter().enumerate() {
+                                let pin = codepilot_code.watch_pins.iter().find(|pin| pin.name == *expression);
+                                ui.monospace(expression);
+                                watch_value_row(ui, pin);
+                                if ui.small_button("x").on_hover_text("Unpin").clicked() {
+                                    unpinned = Some(index);
+                                }
+                                ui.end_row();
+                            }
+                        });
+                        if let Some(index) = unpinned {
+                            codepilot_code.watch_expressions.remove(index);
+                        }
+
+                        ui.separator();
+
+                        egui::Grid::new("codepilot_watch_globals").striped(true).show(ui, |ui| {
+                            for global in codepilot_code.watch_globals.iter() {
+                                ui.monospace(&global.name);
+                                watch_value_row(ui, Some(global));
+                                ui.end_row();
+                            }
+                        });
+                    });
+
+                // numeric dbg() values and the ship channels over time
+                egui::CollapsingHeader::new("Plots")
+                    .id_source("codepilot_plots")
+                    .show(ui, |ui| {
+                        ui.horizontal(|ui| {
+                            ui.label("Window: ");
+                            ui.add(egui::Slider::new(&mut debug_plots.window, 1.0..=60.0).suffix(" s"));
+                            ui.toggle_value(&mut debug_plots.paused, "Pause");
+                            if ui.button("Clear").clicked() {
+                                debug_plots.clear();
+                            }
+                        });
+
+                        let keys = debug_plots.series.keys().cloned().collect::<Vec<_>>();
+                        ui.horizontal_wrapped(|ui| {
+                            for key in keys.iter() {
+                                let mut selected = debug_plots.selected.contains(key);
+                                if ui.toggle_value(&mut selected, key.as_str()).changed() {
+                                    if selected {
+                                        debug_plots.selected.insert(key.clone());
+                                    } else {
+                                        debug_plots.selected.remove(key);
+                                    }
+                                }
+                            }
+                        });
+
+                        egui_plot::Plot::new("codepilot_plot")
+                            .height(160.)
+                            .legend(egui_plot::Legend::default())
+                            .allow_drag(debug_plots.paused)
+                            .allow_zoom(debug_plots.paused)
+                            .show(ui, |plot_ui| {
+                                for key in keys.iter().filter(|key| debug_plots.selected.contains(*key)) {
+                                    let points = egui_plot::PlotPoints::new(debug_plots.visible_samples(key));
+                                    plot_ui.line(egui_plot::Line::new(points).name(key));
+                                }
+                            });
+                    });
+
+                ui.horizontal(|ui| {
+                    ui.label("Slot: ");
+                    let mut slot_to_load = None;
+                    egui::ComboBox::from_id_source("script_slot")
+                        .selected_text(script_slots.current.as_str())
+                        .show_ui(ui, |ui| {
+                            for slot in script_slots.available.iter() {
+                                if ui.selectable_label(*slot == script_slots.current, slot.as_str()).clicked() {
+                                    slot_to_load = Some(slot.clone());
+                                }
+                            }
+                        });
+                    if let Some(slot) = slot_to_load {
+                        load_script_event.send(LoadScriptEvent { slot });
+                    }
+
+                    if ui.button("Save").clicked() {
+                        save_script_event.send(SaveScriptEvent { slot: script_slots.current.clone() });
+                    }
+
+                    ui.add(egui::TextEdit::singleline(&mut script_slots.new_slot_name).hint_text("new slot").desired_width(100.));
+                    if ui.button("Save As").clicked() && !script_slots.new_slot_name.trim().is_empty() {
+                        save_script_event.send(SaveScriptEvent { slot: script_slots.new_slot_name.trim().to_owned() });
+                        script_slots.new_slot_name.clear();
+                    }
+                });
+
+                ui.horizontal(|ui| {
+                    ui.label("Watch File: ");
+                    ui.add(egui::TextEdit::singleline(&mut watched_script.path_input).hint_text("path to a .py file").desired_width(200.));
+
+                    if ui.button("Watch").clicked() {
+                        let path = watched_script.path_input.trim().to_owned();
+                        watched_script.watch((!path.is_empty()).then(|| path.into()));
+                    }
+
+                    if watched_script.path.is_some() && ui.button("Stop").clicked() {
+                        watched_script.watch(None);
+                    }
+                });
+                if let Some(path) = watched_script.path.as_ref() {
+                    ui.label(format!("Reloading main.py from {} on change", path.display()));
+                }
+
     			ui.label("Add Codepilot Code: ");
 
-                
+                // project files, the main file runs every tick and can import the others by name
+                let mut file_to_open = None;
+                ui.horizontal_wrapped(|ui| {
+                    for name in codepilot_code.project_files.keys() {
+                        if ui.selectable_label(*name == codepilot_code.active_file, format!("{name}.py")).clicked() {
+                            file_to_open = Some(name.clone());
+                        }
+                    }
+                });
+
+                ui.horizontal(|ui| {
+                    ui.add(egui::TextEdit::singleline(&mut codepilot_code.new_file_name).hint_text("module name").desired_width(120.));
+
+                    if ui.button("New File").clicked() {
+                        let name = codepilot_code.new_file_name.trim().to_owned();
+                        match codepilot_code.add_file(&name) {
+                            Ok(()) => codepilot_code.new_file_name.clear(),
+                            Err(err) => codepilot_code.compile_result = Some(err),
+                        }
+                    }
+
+                    if codepilot_code.active_file != CODEPILOT_MAIN_FILE && ui.button("Delete File").clicked() {
+                        codepilot_code.remove_active_file();
+                    }
+                });
+
+
+                // paused on a breakpoint, the recorded stops of the tick can be stepped through
+                if let Some(stop) = debugger.current_stop() {
+                    ui.group(|ui| {
+                        let kind = if stop.is_breakpoint { "breakpoint" } else { "step" };
+                        ui.label(format!(
+                            "Paused at {}.py:{} ({kind} {} of {})",
+                            stop.file,
+                            stop.line,
+                            debugger.current.unwrap_or_default() + 1,
+                            debugger.stops.len()
+                        ));
+
+                        ui.horizontal(|ui| {
+                            if ui.button("Step").clicked() {
+                                debugger_action_event.send(DebuggerActionEvent::Step);
+                            }
+                            if ui.button("Continue").clicked() {
+                                debugger_action_event.send(DebuggerActionEvent::Continue);
+                            }
+                            if ui.button("Abort").on_hover_text("Resume without acting on this tick's commands").clicked() {
+                                debugger_action_event.send(DebuggerActionEvent::Abort);
+                            }
+                            if stop.file != codepilot_code.active_file && ui.button(format!("Open {}.py", stop.file)).clicked() {
+                                file_to_open = Some(stop.file.clone());
+                            }
+                        });
+
+                        for (title, variables) in [("Locals", &stop.locals), ("Globals", &stop.globals)] {
+                            egui::CollapsingHeader::new(format!("{title} ({})", variables.len()))
+                                .id_source(("codepilot_debugger", title))
+                                .default_open(title == "Locals")
+                                .show(ui, |ui| {
+                                    for (name, value) in variables.iter() {
+                                        ui.monospace(format!("{name} = {value}"));
+                                    }
+                                });
+                        }
+                    });
+                }
+                if let Some(name) = file_to_open {
+                    codepilot_code.open_file(&name);
+                }
+
                 let language = "py";
                 let theme = egui_extras::syntax_highlighting::CodeTheme::from_memory(ui.ctx());
 
+                // a live parse error in the open file takes priority over the last compile or runtime error
+                let diagnostic = codepilot_code.parse_error()
+                    .or_else(|| {
+                        let py_result = codepilot_code.compile_result.as_deref().or(codepilot_code.py_result.as_deref())?;
+                        let project_modules = codepilot_code.project_files.keys().cloned().collect::<Vec<_>>();
+                        diagnostics::from_traceback(py_result, CODEPILOT_MAIN_FILE, &project_modules)
+                    })
+                    .filter(|diagnostic| diagnostic.file == codepilot_code.active_file);
+
+                let stop_line = debugger.current_stop()
+                    .filter(|stop| stop.file == codepilot_code.active_file)
+                    .map(|stop| stop.line);
+
                 let mut layouter = |ui: &egui::Ui, string: &str, _wrap_width: f32| {
-                    let layout_job = highlight(ui.ctx(), &theme, string, language);
+                    let mut layout_job = highlight(ui.ctx(), &theme, string, language);
                     // layout_job.wrap.max_width = wrap_width; // no wrapping
+
+                    if let Some(line) = stop_line.and_then(|line| diagnostics::line_range(string, line)) {
+                        mark_range(&mut layout_job, line, |format| format.background = STOP_LINE_COLOR);
+                    }
+
+                    if let Some(diagnostic) = &diagnostic {
+                        if let Some(line) = diagnostic.line_range(string) {
+                            mark_range(&mut layout_job, line, |format| format.background = ERROR_LINE_COLOR);
+                        }
+                        if let Some(column) = diagnostic.column_range(string) {
+                            mark_range(&mut layout_job, column, |format| format.underline = egui::Stroke::new(2., ERROR_MARKER_COLOR));
+                        }
+                    }
+
                     ui.fonts(|f| f.layout_job(layout_job))
                 };
 
@@ -227,12 +526,63 @@ fn egui_system(
                 .code_editor()
                 .desired_rows(10)
                 .desired_width(400.)
+                .margin(egui::vec2(EDITOR_MARGIN + EDITOR_GUTTER_WIDTH, 2.))
                 .lock_focus(true)
                 .layouter(&mut layouter)
                 .show(ui);
 
                 let mut response = output.response;
 
+                // clicking the gutter toggles a breakpoint on that line
+                let gutter_x = response.rect.left() + EDITOR_MARGIN..=response.rect.left() + EDITOR_MARGIN + EDITOR_GUTTER_WIDTH;
+                for (index, row) in output.galley.rows.iter().enumerate() {
+                    let line = index + 1;
+                    let row_rect = row.rect.translate(output.text_draw_pos.to_vec2());
+                    let marker = egui::pos2(response.rect.left() + EDITOR_MARGIN + EDITOR_GUTTER_WIDTH / 2., row_rect.center().y);
+
+                    let clicked = ui.input(|i| {
+                        i.pointer.primary_clicked() && i.pointer.interact_pos().is_some_and(|pos| {
+                            gutter_x.contains(&pos.x) && row_rect.y_range().contains(pos.y)
+                        })
+                    });
+                    if clicked {
+                        debugger.toggle_breakpoint(&codepilot_code.active_file, line);
+                    }
+
+                    if debugger.has_breakpoint(&codepilot_code.active_file, line) {
+                        // hollow when the line has no call, the tracer never stops there
+                        let line_text = codepilot_code.raw_code.lines().nth(index).unwrap_or_default();
+                        if line_has_call(line_text) {
+                            ui.painter().circle_filled(marker, 5., BREAKPOINT_COLOR);
+                        } else {
+                            ui.painter().circle_stroke(marker, 4., egui::Stroke::new(1.5, BREAKPOINT_COLOR));
+
+                            let marker_rect = egui::Rect::from_center_size(marker, egui::vec2(EDITOR_GUTTER_WIDTH, row_rect.height()));
+                            if ui.rect_contains_pointer(marker_rect) {
+                                egui::show_tooltip_at_pointer(ui.ctx(), egui::Id::new("codepilot_breakpoint"), |ui| {
+                                    ui.label("Breakpoints only stop on lines that call a function, this one never will");
+                                });
+                            }
+                        }
+                    }
+                }
+
+                // gutter marker on the line with the problem, hovering the line shows the message
+                if let Some(diagnostic) = &diagnostic {
+                    if let Some(row) = output.galley.rows.get(diagnostic.line.saturating_sub(1)) {
+                        let row_rect = row.rect.translate(output.text_draw_pos.to_vec2());
+                        let marker = egui::pos2(response.rect.left() + EDITOR_MARGIN + EDITOR_GUTTER_WIDTH / 2., row_rect.center().y);
+                        ui.painter().circle_filled(marker, 3., ERROR_MARKER_COLOR);
+
+                        let line_rect = egui::Rect::from_x_y_ranges(response.rect.x_range(), row_rect.y_range());
+                        if ui.rect_contains_pointer(line_rect) {
+                            egui::show_tooltip_at_pointer(ui.ctx(), egui::Id::new("codepilot_diagnostic"), |ui| {
+                                ui.label(format!("line {}: {}", diagnostic.line, diagnostic.message));
+                            });
+                        }
+                    }
+                }
+
                 // prioritise focus over defocus in event of both for no particular reason (we shouldn't ever have both)
                 if force_focus {
                     response.request_focus();
@@ -241,7 +591,7 @@ fn egui_system(
                 }
 
                 let mut loc = response.rect.left_top();                
-                loc.x += 3.;
+                loc.x += 3. + EDITOR_GUTTER_WIDTH;
 
                 if let Some(text_cursor_range) = output.cursor_range {
                     let cindex: usize = text_cursor_range.primary.ccursor.index;
@@ -334,7 +684,7 @@ fn egui_system(
 
                 };
 
-                if let Some(py_result) = codepilot_code.py_result.clone() {
+                for py_result in [codepilot_code.compile_result.clone(), codepilot_code.py_result.clone()].into_iter().flatten() {
                     let cleaned_result = py_result
                     .replace(r#"File "<embedded>", "#, "")
                     .replace(r#", in <module>"#, "");
@@ -348,57 +698,171 @@ fn egui_system(
                     .show(ui);
                 }
 
-                let mut text = String::new();
-
-                codepilot_code.codepilot_hist.iter().for_each(|(time, output)| {
+                ui.label("Command History:");
 
-                    match output {
-                        CodePilotOutput::CommandState(command) => {
-                            let fire = command.fire;
+                // timeline over the kept history, dragging it away from the end stops following the latest tick
+                if let Some((first_time, last_time)) = codepilot_code.codepilot_hist.time_range() {
+                    let mut scrub_time = codepilot_code.hist_scrub_time.unwrap_or(last_time).clamp(first_time, last_time);
+                    let mut follow = codepilot_code.hist_scrub_time.is_none();
 
-                            let hist_line = format!("{time:.2} Fire: {fire}");
-                            text.push_str(&hist_line);
-                            text.push_str("\n");
+                    ui.horizontal(|ui| {
+                        let slider = ui.add(egui::Slider::new(&mut scrub_time, first_time..=last_time).suffix(" s").max_decimals(2));
+                        if slider.changed() {
+                            follow = scrub_time >= last_time;
+                        }
+                        ui.toggle_value(&mut follow, "Live");
+                    });
+                    codepilot_code.hist_scrub_time = (!follow).then_some(scrub_time);
+
+                    // everything the script was doing at that moment
+                    let (command_state, debug_messages) = codepilot_code.codepilot_hist.state_at(scrub_time);
+                    ui.group(|ui| {
+                        match command_state {
+                            Some(command) => {
+                                let weapons = command.fire_weapons.iter().map(|weapon| weapon.name()).collect::<Vec<_>>().join(", ");
+                                ui.monospace(format!(
+                                    "Fire: {} Weapons: [{weapons}] Throttle: {:+.2} Turn: {:+.2}",
+                                    command.fire, command.throttle, command.turn
+                                ));
+                            }
+                            None => {
+                                ui.weak("No commands yet");
+                            }
+                        }
 
+                        for message in debug_messages.into_iter().flatten() {
+                            match message {
+                                PyDebugMessage::KeyLessDebug(message) => ui.monospace(format!("Debug: {message}")),
+                                PyDebugMessage::KeyedDebug(message) => ui.monospace(format!("Debug: {} = {}", message.key, message.value)),
+                            };
                         }
-                        CodePilotOutput::DebugMessages(py_debug_messages) => {
+                    });
+                }
 
-                            py_debug_messages.iter().for_each(|py_debug_message| {
+                // only the rows in view are laid out, one per history entry
+                let row_height = ui.text_style_height(&egui::TextStyle::Monospace);
+                let scrub_index = codepilot_code.hist_scrub_time.and_then(|time| codepilot_code.codepilot_hist.index_at(time));
+                let mut scroll_area = egui::ScrollArea::vertical()
+                    .id_source("codepilot_history")
+                    .max_height(200.)
+                    .auto_shrink([false, true])
+                    .stick_to_bottom(scrub_index.is_none());
+                // only when the scrub moves to another row, so the list can still be scrolled by hand
+                if let Some(index) = scrub_index.filter(|&index| codepilot_code.hist_scrolled_index != Some(index)) {
+                    scroll_area = scroll_area.vertical_scroll_offset((index as f32 * (row_height + ui.spacing().item_spacing.y) - 100.).max(0.));
+                }
+                codepilot_code.hist_scrolled_index = scrub_index;
 
-                                match py_debug_message {
-                                    PyDebugMessage::KeyLessDebug(message) => {
-                                        let hist_line = format!("{time:.2} Debug: {message}");
-                                        text.push_str(&hist_line);
-                                        text.push_str("\n");
-                                    }
-                                    PyDebugMessage::KeyedDebug(message) => {
-                                        if message.has_changed {
-                                            let key = message.key.clone();
-                                            let value = message.value.clone();
-                                            let hist_line = format!("{time:.2} Debug: {key} = {value}");
-                                            text.push_str(&hist_line);
-                                            text.push_str("\n");
-                                        } 
-                                    }
-                                }
-                            });
-                        }
-                    }                    
+                let mut clicked = None;
+                scroll_area.show_rows(ui, row_height, codepilot_code.codepilot_hist.len(), |ui, rows| {
+                    for index in rows {
+                        let Some((time, output)) = codepilot_code.codepilot_hist.get(index) else {
+                            continue;
+                        };
 
+                        let row = egui::RichText::new(history_line(*time, output)).monospace();
+                        if ui.selectable_label(scrub_index == Some(index), row).clicked() {
+                            clicked = Some(*time);
+                        }
+                    }
                 });
-
-                ui.label("Command History:");
-                egui::TextEdit::multiline(&mut text.as_str())
-                    .font(egui::TextStyle::Monospace) // for cursor height
-                    .code_editor()
-                    .desired_width(400.)
-                    .show(ui);
+                // the clicked row is already in view
+                if let Some(time) = clicked {
+                    codepilot_code.hist_scrub_time = Some(time);
+                    codepilot_code.hist_scrolled_index = codepilot_code.codepilot_hist.index_at(time);
+                }
 
             });
         });
 
 }
 
+// one line of the command history
+fn history_line(time: f32, output: &CodePilotOutput) -> String {
+    match output {
+        CodePilotOutput::CommandState(command) => {
+            let weapons = command.fire_weapons.iter().map(|weapon| weapon.name()).collect::<Vec<_>>().join(", ");
+            format!(
+                "{time:.2} Fire: {} Weapons: [{weapons}] Throttle: {:+.2} Turn: {:+.2}",
+                command.fire, command.throttle, command.turn
+            )
+        }
+        CodePilotOutput::Console(line) => {
+            let stream = if line.is_error { "!" } else { ">" };
+            match line.repeats {
+                1 => format!("{time:.2} {stream} {}", line.text),
+                repeats => format!("{time:.2} {stream} {} (x{repeats})", line.text),
+            }
+        }
+        // only what changed since the previous tick, the timeline shows all of it
+        CodePilotOutput::DebugMessages(py_debug_messages) => {
+            let messages = py_debug_messages.iter()
+                .filter_map(|py_debug_message| match py_debug_message {
+                    PyDebugMessage::KeyLessDebug(message) => Some(message.clone()),
+                    PyDebugMessage::KeyedDebug(message) => message.has_changed.then(|| format!("{} = {}", message.key, message.value)),
+                })
+                .collect::<Vec<_>>();
+
+            if messages.is_empty() {
+                format!("{time:.2} Debug: unchanged")
+            } else {
+                format!("{time:.2} Debug: {}", messages.join(", "))
+            }
+        }
+    }
+}
+
+// the type and value columns of a watch, values that changed on the last tick are highlighted
+fn watch_value_row(ui: &mut egui::Ui, watch: Option<&WatchValue>) {
+    let Some(watch) = watch else {
+        ui.weak("-");
+        ui.weak("not evaluated yet");
+        return;
+    };
+
+    ui.weak(&watch.type_name);
+
+    let value = egui::RichText::new(&watch.value).monospace();
+    if watch.has_changed {
+        ui.label(value.color(WATCH_CHANGED_COLOR));
+    } else {
+        ui.label(value);
+    }
+}
+
+// applies a format change to a byte range of the job, splitting sections at its ends
+fn mark_range(job: &mut egui::text::LayoutJob, range: std::ops::Range<usize>, mark: impl Fn(&mut egui::TextFormat)) {
+    let mut sections = Vec::with_capacity(job.sections.len() + 2);
+
+    for section in job.sections.drain(..) {
+        let section_range = section.byte_range.clone();
+        let cuts = [
+            section_range.start,
+            range.start.clamp(section_range.start, section_range.end),
+            range.end.clamp(section_range.start, section_range.end),
+            section_range.end,
+        ];
+
+        for piece in cuts.windows(2).filter(|piece| piece[0] < piece[1]) {
+            let mut piece_section = section.clone();
+            piece_section.byte_range = piece[0]..piece[1];
+
+            // only the first piece keeps the section's leading space
+            if piece[0] != section_range.start {
+                piece_section.leading_space = 0.;
+            }
+
+            if range.contains(&piece[0]) {
+                mark(&mut piece_section.format);
+            }
+
+            sections.push(piece_section);
+        }
+    }
+
+    job.sections = sections;
+}
+
 fn spawn_bar(parent: &mut ChildBuilder, asset_server: Res<AssetServer>) {
     parent
         .spawn(NodeBundle {
@@ -468,7 +932,10 @@ fn ui_update_system(
 
 	//Display whether Codepilot is running
 	for mut text in codepilotquery.iter_mut() {
-		if copilotcode.compiled.is_some() {
+		if copilotcode.faulted {
+			text.sections[1].value = format!("Faulted ({:?} to resume)", CODEPILOT_RESUME_KEY);
+			text.sections[1].style.color = Color::ORANGE;
+		} else if copilotcode.compiled.is_some() {
 			text.sections[1].value = format!("Active");
 			text.sections[1].style.color = Color::GREEN;
 		} else {
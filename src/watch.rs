@@ -0,0 +1,91 @@
+use std::{fs, path::PathBuf, time::{Duration, SystemTime}};
+
+use bevy::{prelude::*, time::common_conditions::on_timer};
+
+use crate::{events::CompileCodeEvent, CodePilotCode, CODEPILOT_MAIN_FILE};
+
+const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+
+pub struct ScriptWatchPlugin;
+
+impl Plugin for ScriptWatchPlugin {
+	fn build(&self, app: &mut App) {
+		app
+		.init_resource::<WatchedScript>()
+		.add_systems(Update, script_watch_system.run_if(on_timer(WATCH_INTERVAL)));
+	}
+}
+
+/// Resource - a script file edited outside the game. Whenever it changes on disk it
+/// replaces the main file of the project and gets compiled.
+/// Set with `--watch <path>` on the command line or from the editor panel.
+#[derive(Resource)]
+pub struct WatchedScript {
+	pub path: Option<PathBuf>,
+	pub path_input: String, // as typed in the panel
+	last_modified: Option<SystemTime>,
+	missing_reported: bool, // the file couldn't be found and that's been shown already
+}
+
+impl Default for WatchedScript {
+	fn default() -> Self {
+		let path = std::env::args()
+			.skip_while(|arg| arg != "--watch")
+			.nth(1)
+			.map(PathBuf::from);
+
+		Self {
+			path_input: path.as_ref().map(|path| path.display().to_string()).unwrap_or_default(),
+			path,
+			last_modified: None,
+			missing_reported: false,
+		}
+	}
+}
+
+impl WatchedScript {
+	pub fn watch(&mut self, path: Option<PathBuf>) {
+		self.path = path;
+		// load the new file right away
+		self.last_modified = None;
+		self.missing_reported = false;
+	}
+}
+
+fn script_watch_system(
+	mut watched_script: ResMut<WatchedScript>,
+	mut codepilot_code: ResMut<CodePilotCode>,
+	mut compile_code_event: EventWriter<CompileCodeEvent>,
+) {
+	let Some(path) = watched_script.path.clone() else {
+		return;
+	};
+
+	let modified = match fs::metadata(&path).and_then(|metadata| metadata.modified()) {
+		Ok(modified) => modified,
+		Err(err) => {
+			// only report once, not every poll
+			if !watched_script.missing_reported {
+				codepilot_code.compile_result = Some(format!("Could not watch {}: {err}", path.display()));
+				watched_script.missing_reported = true;
+			}
+			return;
+		}
+	};
+	watched_script.missing_reported = false;
+
+	if watched_script.last_modified == Some(modified) {
+		return;
+	}
+
+	match fs::read_to_string(&path) {
+		Ok(source) => {
+			info!("Reloading codepilot script from {}", path.display());
+			watched_script.last_modified = Some(modified);
+			codepilot_code.set_file_source(CODEPILOT_MAIN_FILE, source);
+			compile_code_event.send(CompileCodeEvent);
+		}
+		// editors often write in several steps, try again on the next poll
+		Err(err) => warn!("Could not read {}: {err}", path.display()),
+	}
+}
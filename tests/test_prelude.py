@@ -0,0 +1,154 @@
+"""Tests for the helpers loaded into every codepilot script.
+
+Run with `python3 -m unittest discover tests` from the repository root.
+The prelude runs in the script's globals, so it's executed into a fresh dict the same way.
+"""
+import math
+import pathlib
+import unittest
+from types import SimpleNamespace
+
+PRELUDE_PATH = pathlib.Path(__file__).resolve().parent.parent / "src" / "codepilot" / "prelude.py"
+
+
+def load_prelude(**script_globals):
+    namespace = dict(script_globals, __name__="__main__")
+    exec(compile(PRELUDE_PATH.read_text(), str(PRELUDE_PATH), "exec"), namespace)
+    return namespace
+
+
+# stand-ins for codepilot.ShipStatus and codepilot.Contact
+def status(position=(0, 0), velocity=(0, 0), heading=0.0, shields=50, max_shields=100):
+    return SimpleNamespace(
+        position=position,
+        velocity=velocity,
+        angular_velocity=0.0,
+        heading=heading,
+        shields=shields,
+        max_shields=max_shields,
+    )
+
+
+def contact(id, position, velocity=(0, 0), allegiance="enemy"):
+    return SimpleNamespace(
+        id=id,
+        position=position,
+        velocity=velocity,
+        heading=0.0,
+        shields=10,
+        max_shields=10,
+        allegiance=allegiance,
+    )
+
+
+class Vec2Test(unittest.TestCase):
+    def setUp(self):
+        self.Vec2 = load_prelude()["Vec2"]
+
+    def test_arithmetic(self):
+        Vec2 = self.Vec2
+        self.assertEqual(Vec2(1, 2) + Vec2(3, 4), Vec2(4, 6))
+        self.assertEqual(Vec2(1, 2) - (3, 4), Vec2(-2, -2))
+        self.assertEqual((3, 4) - Vec2(1, 2), Vec2(2, 2))
+        self.assertEqual(2 * Vec2(1, 2), Vec2(2, 4))
+        self.assertEqual(Vec2(2, 4) / 2, Vec2(1, 2))
+        self.assertEqual(-Vec2(1, -2), Vec2(-1, 2))
+
+    def test_unpacks_like_a_pair(self):
+        x, y = self.Vec2(3, 4)
+        self.assertEqual((x, y), (3, 4))
+        self.assertEqual(self.Vec2(3, 4)[1], 4)
+        self.assertEqual(tuple(self.Vec2(3, 4)[0:2]), (3, 4))
+
+    def test_length_and_normalized(self):
+        Vec2 = self.Vec2
+        self.assertEqual(Vec2(3, 4).length(), 5)
+        self.assertEqual(Vec2(3, 4).length_squared(), 25)
+        self.assertAlmostEqual(Vec2(3, 4).normalized().length(), 1)
+        self.assertEqual(Vec2().normalized(), Vec2())
+        self.assertEqual(Vec2(1, 1).distance_to((4, 5)), 5)
+
+    def test_dot_and_cross(self):
+        Vec2 = self.Vec2
+        self.assertEqual(Vec2(1, 0).dot(Vec2(0, 1)), 0)
+        self.assertEqual(Vec2(2, 3).dot((4, 5)), 23)
+        # +y is counter-clockwise of +x
+        self.assertEqual(Vec2(1, 0).cross(Vec2(0, 1)), 1)
+        self.assertEqual(Vec2(0, 1).cross(Vec2(1, 0)), -1)
+
+    def test_angles(self):
+        Vec2 = self.Vec2
+        self.assertAlmostEqual(Vec2(0, 1).angle(), math.pi / 2)
+        up = Vec2.from_angle(math.pi / 2, 2)
+        self.assertAlmostEqual(up.x, 0)
+        self.assertAlmostEqual(up.y, 2)
+        rotated = Vec2(1, 0).rotated(math.pi / 2)
+        self.assertAlmostEqual(rotated.x, 0)
+        self.assertAlmostEqual(rotated.y, 1)
+
+
+class ShipTest(unittest.TestCase):
+    def test_reads_the_status_global(self):
+        prelude = load_prelude(status=status(position=(10, 20), velocity=(1, 2), heading=math.pi))
+        ship = prelude["Ship"]()
+
+        self.assertEqual(ship.position, prelude["Vec2"](10, 20))
+        self.assertEqual(ship.velocity, prelude["Vec2"](1, 2))
+        self.assertAlmostEqual(ship.heading_vector.x, -1)
+        self.assertAlmostEqual(ship.heading_vector.y, 0)
+        self.assertEqual(ship.shield_fraction, 0.5)
+
+    def test_bearing_is_wrapped(self):
+        prelude = load_prelude()
+        ship = prelude["Ship"](status(heading=math.radians(170)))
+
+        # straight down is 100 degrees counter-clockwise of 170, not 260 clockwise
+        self.assertAlmostEqual(ship.bearing_to((0, -10)), math.radians(100))
+        self.assertAlmostEqual(ship.bearing_to((0, 10)), math.radians(-80))
+
+    def test_enemies_are_sorted_by_distance(self):
+        contacts = [
+            contact(1, (300, 0)),
+            contact(2, (0, 100)),
+            contact(3, (50, 0), allegiance="friendly"),
+        ]
+        prelude = load_prelude(status=status(), enemies=contacts)
+        ship = prelude["Ship"]()
+
+        self.assertEqual([enemy.id for enemy in ship.enemies()], [2, 1])
+        self.assertEqual(ship.nearest_enemy().id, 2)
+        self.assertIsNone(ship.nearest_enemy([]))
+
+
+class EnemyTest(unittest.TestCase):
+    def setUp(self):
+        self.prelude = load_prelude()
+        self.ship = self.prelude["Ship"](status(position=(100, 100), velocity=(5, 0), heading=0.0))
+
+    def test_distance_and_bearing(self):
+        enemy = self.prelude["Enemy"](contact(7, (100, 200)), self.ship)
+
+        self.assertEqual(enemy.offset, self.prelude["Vec2"](0, 100))
+        self.assertEqual(enemy.distance, 100)
+        self.assertAlmostEqual(enemy.bearing, math.pi / 2)
+
+    def test_relative_velocity(self):
+        enemy = self.prelude["Enemy"](contact(7, (200, 100), velocity=(-5, 3)), self.ship)
+
+        self.assertEqual(enemy.relative_velocity, self.prelude["Vec2"](-10, 3))
+        # coming straight at the ship at 10 units/s along the line between them
+        self.assertAlmostEqual(enemy.closing_speed, 10)
+
+
+class DbgTest(unittest.TestCase):
+    def test_collects_keyed_and_keyless_values(self):
+        prelude = load_prelude()
+        prelude["dbg"]("speed", 3)
+        prelude["dbg"](value="hello")
+        prelude["dbg"]("ignored")
+
+        self.assertEqual(prelude["debug_list"], [("speed", 3), ("KeylessDebug_", "hello")])
+
+
+if __name__ == "__main__":
+    unittest.main()